@@ -0,0 +1,87 @@
+use proc_macro::TokenStream;
+use syn::{Data, Expr, Fields, Lit};
+
+/// An enum with `#[derive(BitrangeEnum)]`
+#[derive(Debug)]
+pub struct BitrangeEnum {
+    pub name: String,
+    /// The name and discriminant of every variant, sorted by discriminant
+    pub variants: Vec<(String, u128)>,
+}
+
+impl BitrangeEnum {
+    pub fn from_stream(stream: TokenStream) -> syn::Result<BitrangeEnum> {
+        let ast: syn::DeriveInput = syn::parse(stream)?;
+        let name = format!("{}", ast.ident);
+        let data = match ast.data {
+            Data::Enum(data) => data,
+            _ => {
+                return Err(syn::Error::new(
+                    ast.ident.span(),
+                    format!("{}: BitrangeEnum can only be derived for enums", name),
+                ))
+            }
+        };
+        if data.variants.is_empty() {
+            return Err(syn::Error::new(
+                ast.ident.span(),
+                format!("{}: BitrangeEnum needs at least one variant", name),
+            ));
+        }
+
+        let mut variants = Vec::new();
+        let mut next = 0u128;
+        for variant in &data.variants {
+            if !matches!(variant.fields, Fields::Unit) {
+                return Err(syn::Error::new_spanned(
+                    &variant.fields,
+                    format!(
+                        "{}::{}: BitrangeEnum variants can not have fields",
+                        name, variant.ident
+                    ),
+                ));
+            }
+            let discriminant = match variant.discriminant {
+                Some((_, Expr::Lit(ref expr))) => match expr.lit {
+                    Lit::Int(ref lit) => lit.base10_parse::<u128>()?,
+                    ref lit => {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            format!(
+                                "{}::{}: expected an integer discriminant",
+                                name, variant.ident
+                            ),
+                        ))
+                    }
+                },
+                Some((_, ref expr)) => {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        format!(
+                            "{}::{}: the discriminant of a BitrangeEnum has to be a positive integer literal",
+                            name, variant.ident
+                        ),
+                    ))
+                }
+                None => next,
+            };
+            variants.push((format!("{}", variant.ident), discriminant));
+            next = discriminant.wrapping_add(1);
+        }
+        variants.sort_by_key(|(_, discriminant)| *discriminant);
+
+        Ok(BitrangeEnum { name, variants })
+    }
+
+    /// Returns the amount of bits that are needed to store every discriminant
+    pub fn get_width(&self) -> usize {
+        let max = self.variants.iter().map(|(_, d)| *d).max().unwrap_or(0);
+        (128 - max.leading_zeros() as usize).max(1)
+    }
+
+    /// Returns true if every value of `get_width()` bits is a variant of this enum
+    pub fn is_exhaustive(&self) -> bool {
+        let width = self.get_width();
+        width < 128 && self.variants.len() as u128 == 1u128 << width
+    }
+}
@@ -0,0 +1,275 @@
+use crate::layout::{Entry, Field, Layout};
+use crate::options::{BitNumbering, Options};
+use syn::parse::ParseStream;
+use syn::{Attribute, Ident, LitStr, Token, Type};
+
+/// A cell of the diagram, with its label and the amount of bits it spans
+struct Cell {
+    label: String,
+    width: usize,
+}
+
+/// Parse the input of the `bitrange_diagram!` macro into the same layout as `bitrange!` would have.
+///
+/// ```ignore
+/// bitrange_diagram! {
+///     Test: u8,
+///     r"
+///     +-+-+-+-+-+-+-+-+
+///     |First|1|Second |
+///     +-+-+-+-+-+-+-+-+
+///     "
+/// }
+/// ```
+///
+/// is the same as
+///
+/// ```ignore
+/// bitrange! {
+///     #[bitrange(bit_numbering = "msb0")]
+///     Test: u8,
+///     aaa_1_bbbb,
+///     a: first set_first,
+///     b: second set_second
+/// }
+/// ```
+///
+/// except that the associated constants are named after the fields, e.g. `FIRST_MASK` instead of `A_MASK`,
+/// like in the width form of `bitrange!`.
+pub fn parse(input: ParseStream) -> syn::Result<Layout> {
+    let mut attrs = input.call(Attribute::parse_outer)?;
+    let struct_name: Ident = input.parse()?;
+    let options = Options {
+        bit_numbering: BitNumbering::Msb0,
+        ..Options::default()
+    }
+    .with_attrs(&struct_name.to_string(), &attrs)?;
+    attrs.retain(|attr| !Options::is_option(attr));
+    let attrs = options.strip_derives(attrs);
+    input.parse::<Token![:]>()?;
+    let size: Type = input.parse()?;
+    input.parse::<Token![,]>()?;
+    let diagram: LitStr = input.parse()?;
+    input.parse::<Option<Token![,]>>()?;
+
+    let span = diagram.span();
+    let cells = parse_cells(&diagram.value())
+        .map_err(|e| syn::Error::new(span, format!("{}: {}", struct_name, e)))?;
+    let mut entries = Vec::new();
+    for cell in cells {
+        let bits = cell
+            .label
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        if !bits.is_empty() && bits.chars().all(|c| c == '0' || c == '1') {
+            let bits = if bits.len() == 1 {
+                bits.repeat(cell.width)
+            } else {
+                bits
+            };
+            if bits.len() != cell.width {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "{}: cell {:?} has {} bits, but the label has {} bits",
+                        struct_name,
+                        cell.label,
+                        cell.width,
+                        bits.len()
+                    ),
+                ));
+            }
+            entries.push(Entry::Bits(bits));
+            continue;
+        }
+        entries.push(match get_name(&cell.label) {
+            Some(name) => {
+                let getter = Ident::new(&name, span);
+                let field = Field {
+                    token: getter.clone(),
+                    setter: Some(Ident::new(&format!("set_{}", name), span)),
+                    getter,
+                    signed: false,
+                    ty: None,
+                    order: None,
+                };
+                Entry::Field(Box::new(field), cell.width)
+            }
+            None => Entry::Reserved(cell.width),
+        });
+    }
+    Layout::from_entries(attrs, options, struct_name, size, entries, span)
+}
+
+/// Split the diagram into cells, from the first bit to the last bit.
+///
+/// Every bit is two characters wide. Lines with only digits are the header, and are skipped.
+/// Cells without a `|` between the lines that start with a `+`, span as many rows as there are of those lines, plus one.
+fn parse_cells(diagram: &str) -> Result<Vec<Cell>, String> {
+    let lines = diagram
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end().chars().collect::<Vec<_>>()))
+        .filter(|(_, line)| {
+            line.iter()
+                .any(|c| !c.is_whitespace() && !c.is_ascii_digit())
+        })
+        .collect::<Vec<_>>();
+    let (number, first) = match lines.first() {
+        Some(line) => line,
+        None => return Err("the diagram is empty".to_string()),
+    };
+    let start = first.iter().take_while(|c| c.is_whitespace()).count();
+    if !is_border(&first[start..]) {
+        return Err(format!(
+            "line {} of the diagram should be a border like +-+-+",
+            number
+        ));
+    }
+
+    let mut cells = Vec::new();
+    let mut rows: Vec<(usize, &[char])> = Vec::new();
+    for (number, line) in &lines[1..] {
+        if line.len() <= start || line[..start].iter().any(|c| !c.is_whitespace()) {
+            return Err(format!(
+                "line {} of the diagram starts to the left of the first border",
+                number
+            ));
+        }
+        let line = &line[start..];
+        if !is_border(line) {
+            rows.push((*number, line));
+        } else if !rows.is_empty() {
+            cells.extend(parse_row(&rows)?);
+            rows.clear();
+        }
+    }
+    match rows.first() {
+        Some((number, _)) => Err(format!(
+            "line {} of the diagram should be followed by a border like +-+-+",
+            number
+        )),
+        None => Ok(cells),
+    }
+}
+
+/// Split the lines between two borders into cells
+fn parse_row(rows: &[(usize, &[char])]) -> Result<Vec<Cell>, String> {
+    let (number, first) = rows[0];
+    for &(number, line) in rows {
+        let last = line[line.len() - 1];
+        if line[0] != '|' && line[0] != '+' {
+            return Err(format!(
+                "line {} of the diagram should start with | or +",
+                number
+            ));
+        }
+        if line.len() % 2 == 0 || (last != '|' && last != '+') {
+            return Err(format!(
+                "line {} of the diagram should end with | or + at the end of a bit",
+                number
+            ));
+        }
+        if line.len() != first.len() {
+            return Err(format!(
+                "line {} of the diagram should be as wide as line {}",
+                number, rows[0].0
+            ));
+        }
+    }
+    let bits = first.len() / 2;
+    let label = |from: usize, to: usize| {
+        let pieces = rows
+            .iter()
+            .map(|(_, line)| line[from + 1..to].iter().collect::<String>())
+            .map(|piece| piece.trim().to_string())
+            .filter(|piece| !piece.is_empty())
+            .collect::<Vec<_>>();
+        // Labels that are written from top to bottom, like `U`, `R`, `G` in RFC 793
+        if pieces.iter().all(|piece| piece.chars().count() == 1) {
+            pieces.concat()
+        } else {
+            pieces.join(" ")
+        }
+    };
+
+    // A field that spans several rows of 32 bits, like an IPv6 address
+    let words = 1 + rows.iter().filter(|(_, line)| line[0] == '+').count();
+    if words > 1 {
+        if rows
+            .iter()
+            .any(|(_, line)| line[1..line.len() - 1].contains(&'|'))
+        {
+            return Err(format!(
+                "the cells from line {} of the diagram span several rows, so they can only be a single field",
+                number
+            ));
+        }
+        return Ok(vec![Cell {
+            label: label(0, first.len() - 1),
+            width: bits * words,
+        }]);
+    }
+
+    let mut boundaries = first
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == '|')
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if !boundaries.contains(&(first.len() - 1)) {
+        boundaries.push(first.len() - 1);
+    }
+    let mut cells = Vec::new();
+    for pair in boundaries.windows(2) {
+        if pair[1] % 2 != 0 {
+            return Err(format!(
+                "the | in line {} of the diagram should be between two bits",
+                number
+            ));
+        }
+        cells.push(Cell {
+            label: label(pair[0], pair[1]),
+            width: (pair[1] - pair[0]) / 2,
+        });
+    }
+    Ok(cells)
+}
+
+/// Returns true if the line is a border like `+-+-+`
+fn is_border(line: &[char]) -> bool {
+    line.first() == Some(&'+') && line.iter().all(|&c| c == '+' || c == '-')
+}
+
+/// Returns the name of the getter for the given label, e.g. `type_of_service` for `Type of Service`,
+/// or `None` if the cell is reserved.
+///
+/// Text between parentheses is skipped, so `Reference Timestamp (64)` is `reference_timestamp`.
+fn get_name(label: &str) -> Option<String> {
+    let mut name = String::new();
+    let mut depth = 0;
+    for c in label.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            c if c.is_ascii_alphanumeric() => name.push(c.to_ascii_lowercase()),
+            _ if !name.is_empty() && !name.ends_with('_') => name.push('_'),
+            _ => {}
+        }
+    }
+    let name = name.trim_end_matches('_');
+    if name.is_empty() || name == "reserved" || name == "unused" {
+        return None;
+    }
+    let mut name = match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{}", name),
+        false => name.to_string(),
+    };
+    // Keywords like `type` can not be the name of a function
+    if syn::parse_str::<Ident>(&name).is_err() {
+        name.push('_');
+    }
+    Some(name)
+}
@@ -0,0 +1,909 @@
+use crate::options::Options;
+use crate::pattern::Pattern;
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::ToTokens;
+use std::collections::{HashMap, HashSet};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Ident, LitInt, LitStr, Token, Type};
+
+/// The input of the `bitrange!` macro.
+///
+/// ```ignore
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second set_second
+/// }
+/// ```
+///
+/// The legacy form, where the size is repeated as a string and the pattern is quoted, is also accepted:
+///
+/// ```ignore
+/// bitrange! {
+///     Test: u8, "u8",
+///     "aaa1_0bbb",
+///     a: first,
+///     b: second set_second
+/// }
+/// ```
+///
+/// Every character in the pattern, except for `0` and `1`, has to be mapped to a field,
+/// or has to be marked as reserved with `c: _`.
+///
+/// Instead of a pattern, the fields can also be declared by their amount of bits, from the most significant bit to the least significant bit:
+///
+/// ```ignore
+/// bitrange! {
+///     Test: u8,
+///     first: 3,
+///     _: 0b10,
+///     second: 3 set_second
+/// }
+/// ```
+pub struct Layout {
+    pub attrs: Vec<Attribute>,
+    pub options: Options,
+    pub struct_name: Ident,
+    pub size: Type,
+    pub pattern: TokenStream2,
+    pub fields: Vec<Field>,
+    /// Characters that are explicitly not mapped to a field, with `c: _`
+    pub reserved: Vec<Ident>,
+    /// The prefix of the associated constants of a character, if it is not the character itself.
+    /// This is only used by `bitrange_diagram!`, where the characters are not written by the user
+    pub const_names: HashMap<char, String>,
+}
+
+/// A mapping of a pattern character to a getter, and optionally a setter
+///
+/// By default the value of the field is the smallest integer type that fits all the bits of the field,
+/// or a `bool` if the field is a single bit. A different type can be given with `a: getter setter as u32`,
+/// or `a: getter setter as MyEnum` for an enum with `#[derive(BitrangeEnum)]`.
+///
+/// Fields marked with `signed`, or with a signed integer type, are sign-extended, e.g. `a: offset set_offset signed`
+///
+/// The bits of a field are packed in the order they appear in the pattern, unless the order is given after the getter,
+/// like the immediate of a RISC-V branch: `i: imm[12|10:5|4:1|11] set_imm signed`
+pub struct Field {
+    pub token: Ident,
+    pub getter: Ident,
+    pub setter: Option<Ident>,
+    pub signed: bool,
+    pub ty: Option<Type>,
+    pub order: Option<BitOrder>,
+}
+
+/// The index in the value of every bit of a field, from the first to the last bit of the field in the pattern,
+/// e.g. `[12|10:5|4:1|11]`. Bits of the value below the lowest index are always 0
+pub struct BitOrder {
+    pub bits: Vec<usize>,
+    pub span: Span,
+}
+
+impl Parse for BitOrder {
+    fn parse(input: ParseStream) -> syn::Result<BitOrder> {
+        let content;
+        let bracket = syn::bracketed!(content in input);
+        let mut bits = Vec::new();
+        loop {
+            let high = content.parse::<LitInt>()?;
+            let low = match content.parse::<Option<Token![:]>>()? {
+                Some(_) => content.parse::<LitInt>()?,
+                None => high.clone(),
+            };
+            let (high_bit, low_bit) = (high.base10_parse::<usize>()?, low.base10_parse::<usize>()?);
+            if high_bit < low_bit {
+                return Err(syn::Error::new(
+                    low.span(),
+                    format!(
+                        "expected the highest bit first, like `{}:{}`",
+                        low_bit, high_bit
+                    ),
+                ));
+            }
+            bits.extend((low_bit..=high_bit).rev());
+            if content.is_empty() {
+                break;
+            }
+            content.parse::<Token![|]>()?;
+        }
+        Ok(BitOrder {
+            bits,
+            span: bracket.span,
+        })
+    }
+}
+
+impl Parse for Layout {
+    fn parse(input: ParseStream) -> syn::Result<Layout> {
+        let mut attrs = input.call(Attribute::parse_outer)?;
+        let struct_name: Ident = input.parse()?;
+        let options = Options::from_attrs(&struct_name.to_string(), &attrs)?;
+        attrs.retain(|attr| !Options::is_option(attr));
+        let attrs = options.strip_derives(attrs);
+        input.parse::<Token![:]>()?;
+        let size: Type = input.parse()?;
+        input.parse::<Token![,]>()?;
+
+        // Width form: `Test: u8, first: 3, _: 0b10, second: 3`
+        if (input.peek(Ident) || input.peek(Token![_])) && input.peek2(Token![:]) {
+            let span = input.span();
+            let entries = Punctuated::<Entry, Token![,]>::parse_terminated(input)?;
+            return Layout::from_entries(
+                attrs,
+                options,
+                struct_name,
+                size,
+                entries.into_iter().collect(),
+                span,
+            );
+        }
+
+        // Legacy form: `Test: u8, "u8", "aaaa_bbbb", ...`
+        if input.peek(LitStr) && input.peek2(Token![,]) && input.peek3(LitStr) {
+            input.parse::<LitStr>()?;
+            input.parse::<Token![,]>()?;
+        }
+
+        let mut pattern = TokenStream2::new();
+        if input.peek(LitStr) {
+            input.parse::<LitStr>()?.to_tokens(&mut pattern);
+        } else {
+            while !input.is_empty() && !input.peek(Token![,]) {
+                input.parse::<TokenTree>()?.to_tokens(&mut pattern);
+            }
+        }
+
+        let mut fields = Vec::new();
+        let mut reserved = Vec::new();
+        if input.parse::<Option<Token![,]>>()?.is_some() {
+            for mapping in Punctuated::<Mapping, Token![,]>::parse_terminated(input)? {
+                match mapping {
+                    Mapping::Field(field) => fields.push(*field),
+                    Mapping::Reserved(token) => reserved.push(token),
+                }
+            }
+        }
+
+        Ok(Layout {
+            attrs,
+            options,
+            struct_name,
+            size,
+            pattern,
+            fields,
+            reserved,
+            const_names: HashMap::new(),
+        })
+    }
+}
+
+impl Layout {
+    /// Create a layout from fields that are declared by their amount of bits,
+    /// like the width form of `bitrange!` and the cells of `bitrange_diagram!`.
+    ///
+    /// Every field gets a character in the pattern, and the associated constants are named after the field,
+    /// or `RESERVED`, `RESERVED_2` and so on for reserved bits.
+    /// Two entries whose constants would have the same prefix are an error.
+    /// Entries with the same name are a single field, e.g. a field that continues after another field.
+    pub fn from_entries(
+        attrs: Vec<Attribute>,
+        options: Options,
+        struct_name: Ident,
+        size: Type,
+        entries: Vec<Entry>,
+        span: Span,
+    ) -> syn::Result<Layout> {
+        let error =
+            |message: String| syn::Error::new(span, format!("{}: {}", struct_name, message));
+        let size_string = size.to_token_stream().to_string();
+        let width = Pattern::get_storage_width(&size_string)
+            .or_else(|| Pattern::get_odd_width(&size_string));
+        let bits = entries.iter().map(Entry::width).sum::<usize>();
+        match width {
+            Some(width) if bits > width || (bits < width && !options.short) => {
+                return Err(error(format!(
+                    "the fields have {} bits, but the struct has {} bits",
+                    bits, width
+                )))
+            }
+            _ => {}
+        }
+
+        let mut tokens = ('a'..='z').chain('A'..='Z').chain('α'..='ω');
+        let mut next_token = || match tokens.next() {
+            Some(c) => Ok(Ident::new(&c.to_string(), span)),
+            None => Err(error("a struct can have at most 77 fields".to_string())),
+        };
+        let mut pattern = String::new();
+        let mut fields: Vec<Field> = Vec::new();
+        let mut reserved = Vec::new();
+        let mut const_names = HashMap::new();
+        // The field that every constant prefix belongs to, or `None` for reserved bits
+        let mut const_owners = HashMap::new();
+        for entry in entries {
+            if !pattern.is_empty() {
+                pattern.push('_');
+            }
+            let (token, width) = match entry {
+                Entry::Bits(bits) => {
+                    pattern += &bits;
+                    continue;
+                }
+                Entry::Field(field, width) => {
+                    match fields.iter().find(|f| f.getter == field.getter) {
+                        Some(_) if field.setter.is_some() || field.signed || field.ty.is_some() => {
+                            return Err(syn::Error::new(
+                                field.getter.span(),
+                                format!(
+                                    "{}: field `{}` is already declared, only the amount of bits can be given again",
+                                    struct_name, field.getter
+                                ),
+                            ))
+                        }
+                        Some(existing) => (existing.token.clone(), width),
+                        None => {
+                            let token = next_token()?;
+                            let name = field.getter.to_string().trim_matches('_').to_uppercase();
+                            Layout::check_const_name(
+                                &struct_name,
+                                &const_owners,
+                                &name,
+                                Some(&field.getter),
+                            )?;
+                            const_owners.insert(name.clone(), Some(field.getter.clone()));
+                            const_names.insert(
+                                token.to_string().chars().next().unwrap_or_default(),
+                                name,
+                            );
+                            fields.push(Field {
+                                token: token.clone(),
+                                ..*field
+                            });
+                            (token, width)
+                        }
+                    }
+                }
+                Entry::Reserved(width) => {
+                    let token = next_token()?;
+                    let name = match reserved.len() {
+                        0 => "RESERVED".to_string(),
+                        count => format!("RESERVED_{}", count + 1),
+                    };
+                    Layout::check_const_name(&struct_name, &const_owners, &name, None)?;
+                    const_owners.insert(name.clone(), None);
+                    const_names.insert(token.to_string().chars().next().unwrap_or_default(), name);
+                    reserved.push(token.clone());
+                    (token, width)
+                }
+            };
+            pattern += &token.to_string().repeat(width);
+        }
+
+        let mut tokens = TokenStream2::new();
+        LitStr::new(&pattern, span).to_tokens(&mut tokens);
+        Ok(Layout {
+            attrs,
+            options,
+            struct_name,
+            size,
+            pattern: tokens,
+            fields,
+            reserved,
+            const_names,
+        })
+    }
+}
+
+impl Field {
+    /// Parse the optional setter, `signed` and type that follow the name of the getter
+    fn parse_rest(token: Ident, getter: Ident, input: ParseStream) -> syn::Result<Field> {
+        let mut setter = None;
+        let mut signed = false;
+        while let Some(ident) = input.parse::<Option<Ident>>()? {
+            if ident == "signed" && !signed {
+                signed = true;
+            } else if setter.is_none() && !signed {
+                setter = Some(ident);
+            } else {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected `signed`, `as` or `,` after the name of the setter",
+                ));
+            }
+        }
+        let ty = match input.parse::<Option<Token![as]>>()? {
+            Some(_) => Some(input.parse()?),
+            None => None,
+        };
+        Ok(Field {
+            token,
+            getter,
+            setter,
+            signed,
+            ty,
+            order: None,
+        })
+    }
+
+    /// Returns the pattern character of this field
+    pub fn char(&self) -> char {
+        self.token.to_string().chars().next().unwrap_or_default()
+    }
+
+    /// Returns the type of the value of this field
+    /// Single bit fields are a `bool`, unless a different type is given
+    pub fn get_type(&self, pattern: &Pattern) -> String {
+        match self.ty {
+            Some(ref ty) => ty.to_token_stream().to_string(),
+            None => match (self.signed, pattern.get_token_width(self.char())) {
+                (true, bits) => Pattern::get_smallest_signed_size(bits).to_string(),
+                (false, 1) => "bool".to_string(),
+                (false, bits) => Pattern::get_smallest_size(bits).to_string(),
+            },
+        }
+    }
+
+    /// Returns the amount of bits in the type of this field if it is a signed integer, e.g. `Some(16)` for `i16`
+    pub fn get_signed_width(&self, pattern: &Pattern) -> Option<usize> {
+        Pattern::get_signed_width(&self.get_type(pattern))
+    }
+
+    /// Returns true if the type of this field is not a primitive, but a type that implements `bitrange::Field`,
+    /// like an enum with `#[derive(BitrangeEnum)]`
+    pub fn is_enum(&self, pattern: &Pattern) -> bool {
+        self.ty.is_some() && !Pattern::is_primitive(&self.get_type(pattern))
+    }
+
+    /// Returns the type that is returned by the getter of this field.
+    /// This is the same as `get_type`, except for enums which can return an error when the bits are not a valid variant
+    pub fn get_return_type(&self, pattern: &Pattern) -> String {
+        if self.is_enum(pattern) {
+            format!(
+                "<{} as ::bitrange::Field<{}>>::Output",
+                self.get_type(pattern),
+                pattern.get_token_width(self.char())
+            )
+        } else {
+            self.get_type(pattern)
+        }
+    }
+
+    /// Returns the names of the `toggle_x`, `set_x_on` and `clear_x` functions of a `bool` field with a setter
+    pub fn get_flag_names(&self, pattern: &Pattern) -> Option<(Ident, Ident, Ident)> {
+        if self.setter.is_none() || self.get_type(pattern) != "bool" {
+            return None;
+        }
+        let name = |name: String| Ident::new(&name, self.getter.span());
+        Some((
+            name(format!("toggle_{}", self.getter)),
+            name(format!("set_{}_on", self.getter)),
+            name(format!("clear_{}", self.getter)),
+        ))
+    }
+
+    /// Returns the names of the `try_set_x`, `set_x_wrapping` and `set_x_saturating` functions of a numeric field with a setter
+    pub fn get_setter_names(&self, pattern: &Pattern) -> Option<(Ident, Ident, Ident)> {
+        let setter = self.setter.as_ref()?;
+        if self.get_type(pattern) == "bool" || self.is_enum(pattern) {
+            return None;
+        }
+        let name = |name: String| Ident::new(&name, setter.span());
+        Some((
+            name(format!("try_{}", setter)),
+            name(format!("{}_wrapping", setter)),
+            name(format!("{}_saturating", setter)),
+        ))
+    }
+
+    /// Returns the names of all the functions that are generated for this field
+    pub fn get_names(&self, pattern: &Pattern) -> Vec<Ident> {
+        let mut names = vec![self.getter.clone()];
+        names.extend(self.setter.clone());
+        if let Some((toggle, set_on, clear)) = self.get_flag_names(pattern) {
+            names.extend(vec![toggle, set_on, clear]);
+        }
+        if let Some((try_setter, wrapping, saturating)) = self.get_setter_names(pattern) {
+            names.extend(vec![try_setter, wrapping, saturating]);
+        }
+        names
+    }
+
+    /// Check that the order of the bits has an index for every bit of the field in the pattern,
+    /// and that it has every index between the lowest and the highest exactly once
+    fn validate_order(&self, pattern: &Pattern) -> syn::Result<()> {
+        let order = match self.order {
+            Some(ref order) => order,
+            None => return Ok(()),
+        };
+        let error = |message: String| {
+            Err(syn::Error::new(
+                order.span,
+                format!(
+                    "{}: {} of field `{}`",
+                    pattern.struct_name, message, self.getter
+                ),
+            ))
+        };
+        let bits = pattern.get_token_bits(self.char());
+        if order.bits.len() != bits {
+            return error(format!(
+                "the order has {} bits, but there are {} bits in the pattern",
+                order.bits.len(),
+                bits
+            ));
+        }
+        let low = order.bits.iter().min().copied().unwrap_or_default();
+        let high = order.bits.iter().max().copied().unwrap_or_default();
+        if let Some(bit) = (low..=high).find(|bit| !order.bits.contains(bit)) {
+            return error(format!("bit {} is missing in the order", bit));
+        }
+        if high - low + 1 != bits {
+            let mut seen = HashSet::new();
+            let bit = order.bits.iter().find(|bit| !seen.insert(**bit));
+            return error(format!(
+                "bit {} is more than once in the order",
+                bit.copied().unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_type(&self, pattern: &Pattern) -> syn::Result<()> {
+        let ty = match self.ty {
+            Some(ref ty) => ty,
+            None => return Ok(()),
+        };
+        if self.signed && self.get_signed_width(pattern).is_none() {
+            return Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "{}: field `{}` is signed, expected one of i8, i16, i32, i64 or i128",
+                    pattern.struct_name, self.getter
+                ),
+            ));
+        }
+        // A width like `u4` or `i7` is not a type, so it would be taken for the name of an enum
+        let name = self.get_type(pattern);
+        let is_width = name
+            .strip_prefix(['u', 'i'])
+            .is_some_and(|bits| !bits.is_empty() && bits.chars().all(|c| c.is_ascii_digit()));
+        if is_width && !Pattern::is_primitive(&name) {
+            return Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "{}: invalid type {} for field `{}`, expected an integer type like u8 or i8, a width like {} can only be the size of a struct",
+                    pattern.struct_name, name, self.getter, name
+                ),
+            ));
+        }
+        if self.is_enum(pattern) {
+            return Ok(());
+        }
+        let bits = pattern.get_token_width(self.char());
+        if self.get_type(pattern) == "bool" {
+            if bits == 1 {
+                return Ok(());
+            }
+            return Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "{}: field `{}` has {} bits, only single bit fields can be a bool",
+                    pattern.struct_name, self.getter, bits
+                ),
+            ));
+        }
+        let width = Pattern::get_size_width(&self.get_type(pattern))
+            .or_else(|| self.get_signed_width(pattern));
+        match width {
+            Some(width) if width >= bits => Ok(()),
+            Some(_) => Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "{}: field `{}` has {} bits, which do not fit in {}",
+                    pattern.struct_name,
+                    self.getter,
+                    bits,
+                    ty.to_token_stream()
+                ),
+            )),
+            None => Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "{}: invalid type {} for field `{}`, expected an integer type like u8 or i8",
+                    pattern.struct_name,
+                    ty.to_token_stream(),
+                    self.getter
+                ),
+            )),
+        }
+    }
+}
+
+impl Layout {
+    /// Check that every field maps to a character in the given pattern,
+    /// and that every character in the pattern is mapped exactly once
+    pub fn validate(&self, pattern: &Pattern) -> syn::Result<()> {
+        let mut error: Option<syn::Error> = None;
+        let mut push_error = |e: syn::Error| match error {
+            Some(ref mut error) => error.combine(e),
+            None => error = Some(e),
+        };
+
+        let mut mapped = HashSet::new();
+        let tokens = self.fields.iter().map(|f| &f.token);
+        for token in tokens.chain(self.reserved.iter()) {
+            match Layout::validate_token(pattern, token) {
+                Ok(c) if !mapped.insert(c) => push_error(syn::Error::new(
+                    token.span(),
+                    format!(
+                        "{}: character {:?} of pattern {:?} is mapped more than once",
+                        pattern.struct_name, c, pattern.original_pattern
+                    ),
+                )),
+                Ok(_) => {}
+                Err(e) => push_error(e),
+            }
+        }
+
+        for field in &self.fields {
+            let bits = pattern.get_token_width(field.char());
+            if bits > 128 {
+                push_error(syn::Error::new(
+                    field.token.span(),
+                    format!(
+                        "{}: field `{}` has {} bits, a field can have at most 128 bits",
+                        pattern.struct_name, field.getter, bits
+                    ),
+                ));
+            } else if let Err(e) = field.validate_order(pattern) {
+                push_error(e);
+            } else if let Err(e) = field.validate_type(pattern) {
+                push_error(e);
+            }
+        }
+
+        let mut names = HashSet::new();
+        for field in &self.fields {
+            for name in field.get_names(pattern) {
+                if Layout::is_generated(&name.to_string()) {
+                    push_error(syn::Error::new(
+                        name.span(),
+                        format!(
+                            "{}: the name `{}` is already used by the generated code, choose another name for field `{}`",
+                            pattern.struct_name, name, field.getter
+                        ),
+                    ));
+                } else if !names.insert(name.to_string()) {
+                    push_error(syn::Error::new(
+                        name.span(),
+                        format!(
+                            "{}: the name `{}` is used for more than one field",
+                            pattern.struct_name, name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for c in pattern.trimmed_pattern.chars() {
+            if c == '0' || c == '1' || mapped.contains(&c) || !seen.insert(c) {
+                continue;
+            }
+            push_error(syn::Error::new(
+                pattern.get_token_span(c),
+                format!(
+                    "{}: character {:?} of pattern {:?} is not mapped, map it to a field with `{}: name` or mark it as reserved with `{}: _`",
+                    pattern.struct_name, c, pattern.original_pattern, c, c
+                ),
+            ));
+        }
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that the associated constants of a field or reserved bits do not have the same prefix
+    /// as the constants of an earlier entry, e.g. for the fields `a_b` and `_a_b`,
+    /// or a field `reserved` and reserved bits.
+    fn check_const_name(
+        struct_name: &Ident,
+        owners: &HashMap<String, Option<Ident>>,
+        name: &str,
+        getter: Option<&Ident>,
+    ) -> syn::Result<()> {
+        let (field, other) = match (owners.get(name), getter) {
+            (None, _) => return Ok(()),
+            (Some(Some(existing)), Some(getter)) => (getter, format!("field `{}`", existing)),
+            (Some(Some(existing)), None) => (existing, "the reserved bits".to_string()),
+            (Some(None), Some(getter)) => (getter, "the reserved bits".to_string()),
+            // Reserved bits are numbered, so they can not have the same prefix
+            (Some(None), None) => return Ok(()),
+        };
+        Err(syn::Error::new(
+            field.span(),
+            format!(
+                "{}: the constants of field `{}` would start with `{}_`, like the constants of {}, choose another name",
+                struct_name, field, name, other
+            ),
+        ))
+    }
+
+    /// Returns true if a function with this name is generated for every struct or its builders,
+    /// so it can not be the name of a getter or setter
+    fn is_generated(name: &str) -> bool {
+        matches!(
+            name,
+            "bits"
+                | "into_bits"
+                | "new"
+                | "from"
+                | "try_from_bits"
+                | "from_bits_unchecked"
+                | "from_bits_or_panic"
+                | "from_bits_masked"
+                | "from_be_bytes"
+                | "from_le_bytes"
+                | "from_ne_bytes"
+                | "to_be_bytes"
+                | "to_le_bytes"
+                | "to_ne_bytes"
+                | "builder"
+                | "strict_builder"
+                | "build"
+                | "to_diagram"
+        ) || name.starts_with("__bitrange")
+    }
+
+    /// Check that the given token is a single character of the pattern, and return that character
+    fn validate_token(pattern: &Pattern, token: &Ident) -> syn::Result<char> {
+        let str = token.to_string();
+        let mut chars = str.chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => {
+                return Err(syn::Error::new(
+                    token.span(),
+                    format!(
+                        "{}: field {:?} should be a single character of pattern {:?}",
+                        pattern.struct_name, str, pattern.original_pattern
+                    ),
+                ))
+            }
+        };
+        if c == '0' || c == '1' || !pattern.tokens.contains(&c) {
+            return Err(syn::Error::new(
+                token.span(),
+                format!(
+                    "{}: field {:?} is not found in pattern {:?}",
+                    pattern.struct_name, c, pattern.original_pattern
+                ),
+            ));
+        }
+        Ok(c)
+    }
+}
+
+/// A single entry in the list of fields, either `a: getter[order] [setter] [signed] [as type]` or `a: _`
+enum Mapping {
+    Field(Box<Field>),
+    Reserved(Ident),
+}
+
+impl Parse for Mapping {
+    fn parse(input: ParseStream) -> syn::Result<Mapping> {
+        let token = input.parse()?;
+        input.parse::<Token![:]>()?;
+        if input.parse::<Option<Token![_]>>()?.is_some() {
+            return Ok(Mapping::Reserved(token));
+        }
+        let getter = input.parse()?;
+        let order = match input.peek(syn::token::Bracket) {
+            true => Some(input.parse()?),
+            false => None,
+        };
+        let mut field = Field::parse_rest(token, getter, input)?;
+        field.order = order;
+        Ok(Mapping::Field(Box::new(field)))
+    }
+}
+
+/// A single entry in the width form, either `name: 4 [setter] [signed] [as type]`, `_: 4` or `_: 0b01`
+pub enum Entry {
+    /// A field with the given amount of bits
+    Field(Box<Field>, usize),
+    /// Bits that are not mapped to a field
+    Reserved(usize),
+    /// Bits that are always 0 or 1
+    Bits(String),
+}
+
+impl Entry {
+    /// Returns the amount of bits of this entry
+    pub fn width(&self) -> usize {
+        match *self {
+            Entry::Field(_, width) | Entry::Reserved(width) => width,
+            Entry::Bits(ref bits) => bits.len(),
+        }
+    }
+}
+
+impl Entry {
+    fn parse_width(lit: &LitInt) -> syn::Result<usize> {
+        match lit.base10_parse()? {
+            0 => Err(syn::Error::new(lit.span(), "expected at least 1 bit")),
+            width => Ok(width),
+        }
+    }
+}
+
+impl Parse for Entry {
+    fn parse(input: ParseStream) -> syn::Result<Entry> {
+        if input.parse::<Option<Token![_]>>()?.is_some() {
+            input.parse::<Token![:]>()?;
+            let lit = input.parse::<LitInt>()?;
+            let str = lit.to_string();
+            return match str.strip_prefix("0b") {
+                Some(bits) => Ok(Entry::Bits(
+                    bits.trim_end_matches(lit.suffix()).replace('_', ""),
+                )),
+                None => Ok(Entry::Reserved(Entry::parse_width(&lit)?)),
+            };
+        }
+        let getter: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let lit = input.parse::<LitInt>()?;
+        if lit.to_string().starts_with("0b") {
+            return Err(syn::Error::new(
+                lit.span(),
+                format!(
+                    "expected the amount of bits of `{}`, bits that are always 0 or 1 are written as `_: 0b...`",
+                    getter
+                ),
+            ));
+        }
+        let width = Entry::parse_width(&lit)?;
+        // The character of the field is chosen by `Layout::from_entries`
+        let token = getter.clone();
+        Ok(Entry::Field(
+            Box::new(Field::parse_rest(token, getter, input)?),
+            width,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Layout;
+    use crate::pattern::Pattern;
+    use quote::ToTokens;
+
+    /// Returns the messages of the errors in the given input of `bitrange!`
+    fn errors(input: &str) -> Vec<String> {
+        let layout: Layout = match syn::parse_str(input) {
+            Ok(layout) => layout,
+            Err(e) => return e.into_iter().map(|e| e.to_string()).collect(),
+        };
+        let result = Pattern::new(
+            layout.struct_name.to_string(),
+            layout.size.to_token_stream(),
+            layout.pattern.clone(),
+            &layout.options,
+        )
+        .and_then(|pattern| layout.validate(&pattern));
+        match result {
+            Ok(()) => Vec::new(),
+            Err(e) => e.into_iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid() {
+        assert!(errors("Test: u8, aaa1_0bbb, a: first, b: second set_second").is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_character() {
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first, b: second, a: third"),
+            ["Test: character 'a' of pattern \"aaaa_bbbb\" is mapped more than once"]
+        );
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first, b: second, b: _"),
+            ["Test: character 'b' of pattern \"aaaa_bbbb\" is mapped more than once"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_name() {
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first, b: first"),
+            ["Test: the name `first` is used for more than one field"]
+        );
+        // `try_set_first` is generated for the setter of `a`
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first set_first, b: try_set_first"),
+            ["Test: the name `try_set_first` is used for more than one field"]
+        );
+    }
+
+    #[test]
+    fn test_generated_name() {
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: new, b: second"),
+            ["Test: the name `new` is already used by the generated code, choose another name for field `new`"]
+        );
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first bits, b: build"),
+            [
+                "Test: the name `bits` is already used by the generated code, choose another name for field `first`",
+                "Test: the name `build` is already used by the generated code, choose another name for field `build`",
+            ]
+        );
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: into_bits, b: second"),
+            ["Test: the name `into_bits` is already used by the generated code, choose another name for field `into_bits`"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_const_name() {
+        assert!(errors("Test: u8, a_b: 4, a: 4").is_empty());
+        assert_eq!(
+            errors("Test: u8, reserved: 2, _: 6"),
+            ["Test: the constants of field `reserved` would start with `RESERVED_`, like the constants of the reserved bits, choose another name"]
+        );
+        assert_eq!(
+            errors("Test: u8, _: 2, _: 2, reserved_2: 4"),
+            ["Test: the constants of field `reserved_2` would start with `RESERVED_2_`, like the constants of the reserved bits, choose another name"]
+        );
+        assert_eq!(
+            errors("Test: u8, a_b: 4, _a_b: 4"),
+            ["Test: the constants of field `_a_b` would start with `A_B_`, like the constants of field `a_b`, choose another name"]
+        );
+        assert_eq!(
+            errors("Test: u8, a: 4, A: 4"),
+            ["Test: the constants of field `A` would start with `A_`, like the constants of field `a`, choose another name"]
+        );
+    }
+
+    #[test]
+    fn test_order() {
+        assert!(errors("Test: u8, aaaa_bbbb, a: first[4:1], b: second").is_empty());
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first[3:1], b: second"),
+            ["Test: the order has 3 bits, but there are 4 bits in the pattern of field `first`"]
+        );
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first[4|2:0], b: second"),
+            ["Test: bit 3 is missing in the order of field `first`"]
+        );
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first[3:1|1], b: second"),
+            ["Test: bit 1 is more than once in the order of field `first`"]
+        );
+        assert_eq!(
+            syn::parse_str::<Layout>("Test: u8, aaaa_bbbb, a: first[0:3], b: second")
+                .err()
+                .map(|e| e.to_string()),
+            Some("expected the highest bit first, like `3:0`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unmapped() {
+        assert_eq!(
+            errors("Test: u8, aaaa_bbbb, a: first"),
+            ["Test: character 'b' of pattern \"aaaa_bbbb\" is not mapped, map it to a field with `b: name` or mark it as reserved with `b: _`"]
+        );
+        assert_eq!(
+            errors("Test: u8, aaaa_2222, a: first"),
+            ["Test: invalid character '2' in pattern \"aaaa_2222\", only 0 and 1 can be digits, use a letter for a field"]
+        );
+    }
+}
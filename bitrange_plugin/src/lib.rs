@@ -1,105 +1,1641 @@
 extern crate proc_macro;
 
+mod bitrange_enum;
+mod diagram;
+mod layout;
+mod options;
 mod pattern;
 
-use pattern::Pattern;
+use bitrange_enum::BitrangeEnum;
+use layout::{Field, Layout};
+use options::{Options, DERIVES};
+use pattern::{Pattern, Run};
 use proc_macro::TokenStream;
+use quote::ToTokens;
+use std::collections::HashSet;
 use std::str::FromStr;
+use syn::Ident;
 
-#[proc_macro_derive(Bitrange, attributes(BitrangeMask, BitrangeSize))]
+/// Allow an enum to be used as the type of a field, with `b: protocol as Protocol`.
+///
+/// The variants can not have fields, and their discriminants have to be positive integer literals.
+/// The field has to be wide enough to hold the largest discriminant.
+/// If every value of the field is a variant, the getter returns the enum,
+/// otherwise it returns `Result<Protocol, bitrange::InvalidValue>`.
+///
+/// ```ignore
+/// #[derive(BitrangeEnum)]
+/// enum Protocol {
+///     Icmp = 1,
+///     Tcp = 6,
+///     Udp = 17,
+/// }
+/// ```
+#[proc_macro_derive(BitrangeEnum)]
+pub fn derive_bitrange_enum(input: TokenStream) -> TokenStream {
+    let bitrange_enum = match BitrangeEnum::from_stream(input) {
+        Ok(bitrange_enum) => bitrange_enum,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let str = generate_enum(&bitrange_enum);
+
+    TokenStream::from_str(&str).unwrap()
+}
+
+/// Create a bitrange struct.
+///
+/// The size of the internal integer is only given once, and the pattern does not have to be quoted:
+///
+/// ```ignore
+/// bitrange! {
+///     Test: u8,               // the name of the struct and the size of the internal integer
+///     aaa1_0bbb,              // the format of the bits in the internal integer
+///     a: first,               // map the bits that are marked as `a` to field `first`
+///     b: second set_second    // map the bits that are marked as `b` to field `second`
+///                             // and create a setter `set_second` that sets a given value to `b`
+/// }
+/// ```
+///
+/// The fields can also be declared by their amount of bits, as in `Test: u8, first: 3, _: 0b10, second: 3 set_second`.
+/// The legacy `Test: u8, "u8", "aaa1_0bbb", ...` form is still accepted.
+#[proc_macro]
 pub fn bitrange(input: TokenStream) -> TokenStream {
-    let pattern = Pattern::from_stream(input).expect("Could not parse mask");
+    let layout = syn::parse_macro_input!(input as Layout);
+    generate_bitrange(layout)
+}
+
+/// Create a bitrange struct from an RFC style diagram.
+///
+/// The width of every field is taken from the `|` between the cells, and the name of the getter from the label.
+/// Every field gets a setter named `set_` and the name of the getter. Empty cells and cells labeled
+/// `Reserved` or `Unused` are reserved, cells with only `0` or `1` are fixed bits.
+/// The bits are numbered from the first bit of the diagram, unless `#[bitrange(bit_numbering = "lsb0")]` is given.
+///
+/// ```ignore
+/// bitrange_diagram! {
+///     Ipv4Flags: u16,
+///     r"
+///      0                   1
+///      0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+///     +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///     |0|D|M|     Fragment Offset     |
+///     +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///     "
+/// }
+/// ```
+#[proc_macro]
+pub fn bitrange_diagram(input: TokenStream) -> TokenStream {
+    let layout = syn::parse_macro_input!(input with diagram::parse);
+    generate_bitrange(layout)
+}
+
+fn generate_bitrange(layout: Layout) -> TokenStream {
+    let mut pattern = match Pattern::new(
+        layout.struct_name.to_string(),
+        layout.size.to_token_stream(),
+        layout.pattern.clone(),
+        &layout.options,
+    ) {
+        Ok(pattern) => pattern,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    pattern.const_names = layout.const_names.clone();
+    for field in &layout.fields {
+        if let Some(order) = &field.order {
+            pattern.bit_orders.insert(field.char(), order.bits.clone());
+        }
+    }
+    // Errors in the field mapping are reported next to the generated struct,
+    // so they don't cause follow-up errors in code that uses the struct
+    let errors = layout.validate(&pattern).err();
+    let mut names = HashSet::new();
+    let fields = layout
+        .fields
+        .iter()
+        .filter(|field| {
+            if pattern.get_token_width(field.char()) > 128 {
+                return false;
+            }
+            let names_in_field = field.get_names(&pattern);
+            names_in_field
+                .iter()
+                .all(|name| names.insert(name.to_string()))
+        })
+        .collect::<Vec<_>>();
+
+    let mut attrs = String::new();
+    for attr in &layout.attrs {
+        attrs += &attr.to_token_stream().to_string();
+        attrs += "\n";
+    }
+
+    let (derive, debug) = generate_derives(&pattern, &layout.options, &fields);
 
     let str = format!(
         r#"
+{attrs}{derive}
+pub struct {struct_name} {{
+    #[allow(dead_code)]
+    bits: {size}
+}}
+{consts}
+impl Default for {struct_name} {{
+    #[allow(dead_code)]
+    fn default() -> {struct_name} {{
+        {struct_name} {{
+            bits: {struct_name}::__bitrange_get_default_value()
+        }}
+    }}
+}}
+impl ::core::convert::TryFrom<{size}> for {struct_name} {{
+    type Error = ::bitrange::Error<{size}>;
+
+    fn try_from(bits: {size}) -> Result<{struct_name}, ::bitrange::Error<{size}>> {{
+        {struct_name}::try_from_bits(bits)
+    }}
+}}
+impl From<{struct_name}> for {size} {{
+    fn from(value: {struct_name}) -> {size} {{
+        value.bits
+    }}
+}}
+#[allow(clippy::manual_range_contains)]
 impl {struct_name} {{
-    {get_mask}
-    {get_offset}
-    {get_default_mask}
-    {get_default_value}
+    /// Create a value from the bits of the internal integer,
+    /// or return an error if the bits that are always 0 or 1 do not match the pattern
+    #[allow(dead_code)]
+    #[deprecated(since = "0.4.0", note = "use `{struct_name}::try_from_bits(bits)` or `{struct_name}::try_from(bits)` instead")]
+    pub const fn from(bits: {size}) -> Result<{struct_name}, ::bitrange::Error<{size}>> {{
+        {struct_name}::try_from_bits(bits)
+    }}
+
+    /// Create a value from the bits of the internal integer,
+    /// or return an error if the bits that are always 0 or 1 do not match the pattern
+    #[allow(dead_code)]
+    pub const fn try_from_bits(bits: {size}) -> Result<{struct_name}, ::bitrange::Error<{size}>> {{
+        let default_value: {size} = {struct_name}::__bitrange_get_default_value();
+        let default_mask: {size} = {struct_name}::__bitrange_get_default_mask();
+
+        if {is_valid} {{
+            Ok({struct_name} {{
+                bits
+            }})
+        }} else {{
+            Err(::bitrange::Error::invalid_bits(
+                "{struct_name}",
+                default_mask,
+                default_value,
+                {provided},
+                {xor},
+            ).with_numbering({numbering}, {width}))
+        }}
+    }}
+
+    /// Create a value from the bits of the internal integer,
+    /// and panic if the bits that are always 0 or 1 do not match the pattern
+    #[allow(dead_code)]
+    pub const fn from_bits_or_panic(bits: {size}) -> {struct_name} {{
+        match {struct_name}::try_from_bits(bits) {{
+            Ok(value) => value,
+            Err(_) => panic!("{struct_name}: the bits that are always 0 or 1 do not match the pattern"),
+        }}
+    }}
+
+    /// Create a value from the bits of the internal integer, and set the bits that are always 0 or 1 to their value
+    #[allow(dead_code)]
+    pub const fn from_bits_masked(bits: {size}) -> {struct_name} {{
+        {struct_name} {{
+            bits: {masked}
+        }}
+    }}
+
+    /// Create a value from the bits of the internal integer, without checking the bits that are always 0 or 1
+    #[allow(dead_code)]
+    pub const fn from_bits_unchecked(bits: {size}) -> {struct_name} {{
+        {struct_name} {{ bits }}
+    }}
+
+    /// Returns the bits of the internal integer
+    #[allow(dead_code)]
+    pub const fn bits(&self) -> {size} {{
+        self.bits
+    }}
+
+    /// Returns the bits of the internal integer
+    #[allow(dead_code)]
+    pub const fn into_bits(self) -> {size} {{
+        self.bits
+    }}
+{new}
+{fields}
 }}
+{builders}
+{debug}
+{diagram}
+{formats}
+{bytes}
+{nested}
 "#,
+        attrs = attrs,
+        derive = derive,
+        debug = debug,
         struct_name = pattern.struct_name,
-        get_mask = generate_mask(&pattern),
-        get_offset = generate_offset(&pattern),
-        get_default_mask = generate_default_mask(&pattern),
-        get_default_value = generate_default_value(&pattern),
+        size = pattern.size,
+        is_valid = generate_eq(
+            &pattern,
+            &generate_and(&pattern, "bits", "default_mask"),
+            "default_value"
+        ),
+        provided = generate_and(&pattern, "bits", "default_mask"),
+        xor = generate_xor(
+            &pattern,
+            "default_value",
+            &generate_and(&pattern, "bits", "default_mask")
+        ),
+        masked = generate_or(
+            &pattern,
+            &generate_and(
+                &pattern,
+                "bits",
+                &generate_not(
+                    &pattern,
+                    &format!("{}::__bitrange_get_default_mask()", pattern.struct_name)
+                )
+            ),
+            &format!("{}::__bitrange_get_default_value()", pattern.struct_name)
+        ),
+        numbering = layout.options.bit_numbering.path(),
+        width = pattern.get_width(),
+        consts = generate_consts(&pattern, &layout.options, &fields),
+        new = generate_new(&pattern, &fields),
+        fields = fields
+            .iter()
+            .map(|field| generate_field(&pattern, field))
+            .collect::<String>(),
+        builders = generate_builders(&pattern, &fields),
+        diagram = generate_diagram(&pattern, &layout.options, &fields),
+        formats = generate_formats(&pattern, &layout.options, &fields),
+        bytes = generate_bytes(&pattern, &layout.options),
+        nested = generate_nested(&pattern, &layout.options),
     );
 
-    // println!("{}", str);
-    TokenStream::from_str(&str).unwrap()
+    let mut output = TokenStream::from_str(&str).unwrap();
+    if let Some(errors) = errors {
+        output.extend(TokenStream::from(errors.to_compile_error()));
+    }
+    output
 }
 
-fn generate_mask(pattern: &Pattern) -> String {
+/// Generate the `#[derive(...)]` attribute of the struct, and the `Debug` implementation that prints every field.
+/// All the traits in `options::DERIVES` are implemented, unless `#[bitrange(derive(...))]` is given.
+fn generate_derives(pattern: &Pattern, options: &Options, fields: &[&Field]) -> (String, String) {
+    let derive = DERIVES
+        .iter()
+        .filter(|name| {
+            matches!(**name, "Clone" | "Copy" | "PartialEq" | "Eq" | "Hash")
+                && options.derives(name)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let derive = if derive.is_empty() {
+        String::new()
+    } else {
+        format!("#[derive({})]", derive.join(", "))
+    };
+    if !options.derives("Debug") {
+        return (derive, String::new());
+    }
+
+    let mut debug_fields = String::new();
+    for field in fields {
+        debug_fields += &format!(
+            "\n            .field(\"{}\", &{})",
+            field.getter,
+            generate_debug_value(pattern, field)
+        );
+    }
+    let debug = format!(
+        r#"
+impl ::core::fmt::Debug for {struct_name} {{
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        f.debug_struct("{struct_name}"){debug_fields}
+            .finish()
+    }}
+}}
+"#,
+        struct_name = pattern.struct_name,
+        debug_fields = debug_fields,
+    );
+    (derive, debug)
+}
+
+/// Generate an expression with the value of `field` that implements `Debug`.
+/// Enums are printed with `Field::fmt_bits`, so they don't have to implement `Debug`
+fn generate_debug_value(pattern: &Pattern, field: &Field) -> String {
+    if field.is_enum(pattern) {
+        format!(
+            "::bitrange::DebugField::<{}, {}>::new({})",
+            field.get_type(pattern),
+            pattern.get_token_width(field.char()),
+            generate_cast(
+                &generate_gather(
+                    pattern,
+                    &pattern.get_token_runs(field.char()),
+                    "self.bits",
+                    pattern.get_token_bits_type(field.char())
+                ),
+                pattern.get_token_bits_type(field.char()),
+                "u128"
+            )
+        )
+    } else {
+        format!("self.{}()", field.getter)
+    }
+}
+
+/// Generate `to_diagram`, and the `Display` implementation that draws the diagram with rows of 32 bits.
+///
+/// Every run of consecutive bits of a field is a cell in the diagram. Consecutive bits that are always 0 or 1
+/// are grouped in a single cell without a name. Flags are drawn as `0` or `1`, so they fit in a single bit.
+fn generate_diagram(pattern: &Pattern, options: &Options, fields: &[&Field]) -> String {
+    let mut runs: Vec<(char, usize)> = Vec::new();
+    for c in pattern.trimmed_pattern.chars() {
+        let is_default = |token: char| token == '0' || token == '1';
+        match runs.last_mut() {
+            Some((token, bits)) if *token == c || (is_default(*token) && is_default(c)) => {
+                *bits += 1
+            }
+            _ => runs.push((c, 1)),
+        }
+    }
+
+    let mut cells = String::new();
     let mut case_statements = String::new();
-    let mut examples = String::new();
-    for token in &pattern.tokens {
-        let mask = pattern.get_token_mask(*token);
-        case_statements += &format!("            \"{}\" => {},\n", token, mask);
-        examples += &format!(
-            "    /// assert_eq!({}, {}::__bitrange_get_mask(\"{}\"));\n",
-            mask, pattern.struct_name, token
+    let mut offset = 0;
+    for (index, (token, bits)) in runs.iter().enumerate() {
+        let field = fields.iter().find(|field| field.char() == *token);
+        let name = field
+            .map(|field| field.getter.to_string())
+            .unwrap_or_default();
+        cells += &format!(
+            "\n            ::bitrange::DiagramCell {{ name: \"{}\", bits: {} }},",
+            name, bits
         );
+        let value = match field {
+            Some(field) if field.get_type(pattern) == "bool" => {
+                format!(
+                    "::core::fmt::Debug::fmt(&(self.{}() as u8), f)",
+                    field.getter
+                )
+            }
+            Some(field) if field.is_enum(pattern) => format!(
+                "::core::fmt::Debug::fmt(&::bitrange::DiagramField::<{}, {}>::new({}), f)",
+                field.get_type(pattern),
+                pattern.get_token_width(field.char()),
+                generate_cast(
+                    &generate_gather(
+                        pattern,
+                        &pattern.get_token_runs(field.char()),
+                        "self.bits",
+                        pattern.get_token_bits_type(field.char())
+                    ),
+                    pattern.get_token_bits_type(field.char()),
+                    "u128"
+                )
+            ),
+            Some(field) => format!(
+                "::core::fmt::Debug::fmt(&{}, f)",
+                generate_debug_value(pattern, field)
+            ),
+            None if *token == '0' || *token == '1' => format!(
+                "f.write_str(\"{}\")",
+                pattern
+                    .trimmed_pattern
+                    .chars()
+                    .skip(offset)
+                    .take(*bits)
+                    .collect::<String>()
+            ),
+            // Reserved bits are printed as they are, unless they do not fit in a `u128`
+            None if pattern.get_token_width(*token) > 128 => "Ok(())".to_string(),
+            None => format!(
+                "::core::fmt::Debug::fmt(&{}, f)",
+                generate_gather(
+                    pattern,
+                    &pattern.get_token_runs(*token),
+                    "self.bits",
+                    pattern.get_bits_type()
+                )
+            ),
+        };
+        case_statements += &format!("            {} => {},\n", index, value);
+        offset += bits;
     }
 
-    format!(
+    let mut str = format!(
         r#"
-    /// Create a mask based on a given format and a character
-    /// This will map all the bits that match the given character, to 1
-    /// All other bits will be set to 0
-    /// 
-    /// ```
-{examples}
-    /// ```
-    pub fn __bitrange_get_mask(c: &str) -> {size} {{
-        match c {{
+impl {struct_name} {{
+    /// Draw the fields in an RFC style diagram, with `row_bits` bits in every row
+    #[allow(dead_code)]
+    pub fn to_diagram(&self, row_bits: ::bitrange::RowBits) -> ::bitrange::Diagram<'_, {struct_name}> {{
+        const CELLS: &[::bitrange::DiagramCell] = &[{cells}
+        ];
+        ::bitrange::Diagram::new(self, CELLS, {struct_name}::__bitrange_fmt_cell, row_bits, {numbering}, {width})
+    }}
+
+    fn __bitrange_fmt_cell(&self, cell: usize, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        match cell {{
 {case_statements}
-            _ => panic!("Invalid mask character (__bitrange_get_mask): {{:?}}", c),
+            _ => Ok(()),
         }}
     }}
+}}
 "#,
+        struct_name = pattern.struct_name,
+        cells = cells,
         case_statements = case_statements,
-        examples = examples,
+        numbering = options.bit_numbering.path(),
+        width = pattern.get_width(),
+    );
+    if options.derives("Display") {
+        str += &format!(
+            r#"
+impl ::core::fmt::Display for {struct_name} {{
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        ::core::fmt::Display::fmt(&self.to_diagram(::bitrange::RowBits::B32), f)
+    }}
+}}
+"#,
+            struct_name = pattern.struct_name,
+        );
+    }
+    str
+}
+
+/// Generate the `Binary`, `LowerHex`, `UpperHex` and `Octal` implementations, which format the internal integer.
+/// `{:#b}` prints the bits grouped like the pattern, with the pattern and the name of every field below them.
+fn generate_formats(pattern: &Pattern, options: &Options, fields: &[&Field]) -> String {
+    let legend = pattern
+        .get_field_tokens()
+        .iter()
+        .map(
+            |token| match fields.iter().find(|field| field.char() == *token) {
+                Some(field) => format!("{}: {}", token, field.getter),
+                None => format!("{}: _", token),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut str = String::new();
+    for format in &["Binary", "LowerHex", "UpperHex", "Octal"] {
+        if !options.derives(format) {
+            continue;
+        }
+        let grouped = if *format == "Binary" {
+            format!(
+                r#"if f.alternate() {{
+            return ::bitrange::fmt_pattern_bits(self.bits, {width}, "{pattern}", "{legend}", f);
+        }}
+        "#,
+                width = pattern.get_width(),
+                pattern = pattern.original_pattern,
+                legend = legend,
+            )
+        } else {
+            String::new()
+        };
+        // A `[u8; N]` does not implement these traits, so its digits are written one at a time
+        let fmt = if pattern.is_array() {
+            let (digit_bits, upper, prefix) = match *format {
+                "Binary" => (1, false, "0b"),
+                "LowerHex" => (4, false, "0x"),
+                "UpperHex" => (4, true, "0x"),
+                _ => (3, false, "0o"),
+            };
+            format!(
+                "::bitrange::fmt_bits(self.bits, {}, {}, \"{}\", f)",
+                digit_bits, upper, prefix
+            )
+        } else {
+            format!("::core::fmt::{}::fmt(&self.bits, f)", format)
+        };
+        str += &format!(
+            r#"
+impl ::core::fmt::{format} for {struct_name} {{
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        {grouped}{fmt}
+    }}
+}}
+"#,
+            format = format,
+            struct_name = pattern.struct_name,
+            grouped = grouped,
+            fmt = fmt,
+        );
+    }
+    str
+}
+
+/// Generate the conversions from and to the bytes of the internal integer in every byte order,
+/// and `TryFrom<&[u8]>` and `From<Struct> for [u8; N]` in the byte order of `#[bitrange(byte_order = "...")]`
+fn generate_bytes(pattern: &Pattern, options: &Options) -> String {
+    let bytes = pattern.get_bytes();
+    // A width like `u24` has less bytes than its internal integer, the bytes above it are always 0
+    let padding = match Pattern::get_size_width(&pattern.size) {
+        Some(width) => width / 8 - bytes,
+        None => 0,
+    };
+    let indexes = |range: std::ops::Range<usize>| {
+        range
+            .map(|i| format!("bytes[{}]", i))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let zeros = vec!["0"; padding].join(", ");
+    let mut functions = String::new();
+    for (suffix, name) in &[("be", "big"), ("le", "little"), ("ne", "native")] {
+        // The first byte of a `[u8; N]` is the most significant, so it is stored in big-endian byte order
+        let (from_bytes, to_bytes) = match (pattern.is_array(), *suffix) {
+            (false, _) if padding == 0 => (
+                format!(
+                    "{}::try_from_bits({}::from_{}_bytes(bytes))",
+                    pattern.struct_name, pattern.size, suffix
+                ),
+                format!("self.bits.to_{}_bytes()", suffix),
+            ),
+            (false, "be") => (
+                format!(
+                    "{}::try_from_bits({}::from_be_bytes([{}, {}]))",
+                    pattern.struct_name,
+                    pattern.size,
+                    zeros,
+                    indexes(0..bytes)
+                ),
+                format!(
+                    "{{ let bytes = self.bits.to_be_bytes(); [{}] }}",
+                    indexes(padding..padding + bytes)
+                ),
+            ),
+            (false, "le") => (
+                format!(
+                    "{}::try_from_bits({}::from_le_bytes([{}, {}]))",
+                    pattern.struct_name,
+                    pattern.size,
+                    indexes(0..bytes),
+                    zeros
+                ),
+                format!(
+                    "{{ let bytes = self.bits.to_le_bytes(); [{}] }}",
+                    indexes(0..bytes)
+                ),
+            ),
+            (false, _) => (
+                format!(
+                    "if cfg!(target_endian = \"big\") {{ {struct_name}::from_be_bytes(bytes) }} else {{ {struct_name}::from_le_bytes(bytes) }}",
+                    struct_name = pattern.struct_name
+                ),
+                "if cfg!(target_endian = \"big\") { self.to_be_bytes() } else { self.to_le_bytes() }"
+                    .to_string(),
+            ),
+            (true, "be") => (
+                format!("{}::try_from_bits(bytes)", pattern.struct_name),
+                "self.bits".to_string(),
+            ),
+            (true, "le") => (
+                format!(
+                    "{}::try_from_bits(::bitrange::bytes::reverse(bytes))",
+                    pattern.struct_name
+                ),
+                "::bitrange::bytes::reverse(self.bits)".to_string(),
+            ),
+            (true, _) => (
+                format!(
+                    "{}::try_from_bits(::bitrange::bytes::native(bytes))",
+                    pattern.struct_name
+                ),
+                "::bitrange::bytes::native(self.bits)".to_string(),
+            ),
+        };
+        functions += &format!(
+            r#"
+    /// Create a value from the bytes of the internal integer in {name}-endian byte order,
+    /// or return an error if the bits that are always 0 or 1 do not match the pattern
+    #[allow(dead_code)]
+    pub const fn from_{suffix}_bytes(bytes: [u8; {bytes}]) -> Result<{struct_name}, ::bitrange::Error<{size}>> {{
+        {from_bytes}
+    }}
+
+    /// Returns the bytes of the internal integer in {name}-endian byte order
+    #[allow(dead_code)]
+    pub const fn to_{suffix}_bytes(&self) -> [u8; {bytes}] {{
+        {to_bytes}
+    }}
+"#,
+            suffix = suffix,
+            from_bytes = from_bytes,
+            to_bytes = to_bytes,
+            name = name,
+            bytes = bytes,
+            struct_name = pattern.struct_name,
+            size = pattern.size,
+        );
+    }
+    format!(
+        r#"
+impl {struct_name} {{{functions}}}
+
+impl ::core::convert::TryFrom<&[u8]> for {struct_name} {{
+    type Error = ::bitrange::BytesError<{size}>;
+
+    fn try_from(bytes: &[u8]) -> Result<{struct_name}, ::bitrange::BytesError<{size}>> {{
+        if bytes.len() != {bytes} {{
+            return Err(::bitrange::BytesError::Length {{
+                name: "{struct_name}",
+                expected: {bytes},
+                provided: bytes.len(),
+            }});
+        }}
+        let mut array = [0u8; {bytes}];
+        array.copy_from_slice(bytes);
+        Ok({struct_name}::from_{suffix}_bytes(array)?)
+    }}
+}}
+{into_bytes}"#,
+        struct_name = pattern.struct_name,
         size = pattern.size,
+        bytes = bytes,
+        functions = functions,
+        suffix = options.byte_order.suffix(),
+        // `From<Struct> for [u8; N]` is already implemented for the internal integer of a `[u8; N]`
+        into_bytes = if pattern.is_array() {
+            String::new()
+        } else {
+            format!(
+                r#"
+impl From<{struct_name}> for [u8; {bytes}] {{
+    fn from(value: {struct_name}) -> [u8; {bytes}] {{
+        value.to_{suffix}_bytes()
+    }}
+}}
+"#,
+                struct_name = pattern.struct_name,
+                bytes = bytes,
+                suffix = options.byte_order.suffix(),
+            )
+        },
     )
 }
 
-fn generate_offset(pattern: &Pattern) -> String {
-    let mut examples = String::new();
-    let mut case_statements = String::new();
-    for token in &pattern.tokens {
-        let offset = pattern.get_token_offset(*token);
-        let mask = pattern.get_token_mask(*token);
-        case_statements += &format!("            \"{}\" => {}, // {}\n", token, offset, mask);
-        examples += &format!(
-            "    /// assert_eq!({}, {}::__bitrange_get_offset(\"{}\")); // {}\n",
-            offset, pattern.struct_name, token, mask
+/// Generate the associated constants of every field, and the default mask and value.
+/// The `Field::FITS` of every enum or bitrange struct field is evaluated here, so a field that is too narrow is an error
+/// `FIRST_BIT` and `LAST_BIT` are in the bit numbering of the struct, `OFFSET` is always counted from the least significant bit
+fn generate_consts(pattern: &Pattern, options: &Options, fields: &[&Field]) -> String {
+    let mut consts = String::new();
+    let bits = pattern.trimmed_pattern.chars().count();
+    for token in pattern.get_field_tokens() {
+        let index = |bit: usize| options.bit_numbering.index(bit, pattern.get_width());
+        let first = pattern
+            .trimmed_pattern
+            .chars()
+            .position(|c| c == token)
+            .unwrap_or_default();
+        consts += &format!(
+            r#"
+    /// The bits of `{token}` in the pattern
+    pub const {name}_MASK: {size} = {mask};
+    /// The index of the least significant bit of `{token}`, counted from the least significant bit.
+    /// This is the amount of bits that `{token}` is shifted
+    pub const {name}_OFFSET: usize = {offset};
+    /// The amount of bits of `{token}` in the pattern, which are the bits in `{name}_MASK`
+    pub const {name}_WIDTH: usize = {width};
+    /// The index of the first bit of `{token}` in the pattern, in the bit numbering of the struct
+    pub const {name}_FIRST_BIT: usize = {first};
+    /// The index of the last bit of `{token}` in the pattern, in the bit numbering of the struct
+    pub const {name}_LAST_BIT: usize = {last};
+"#,
+            first = index(bits - 1 - first),
+            last = index(pattern.get_token_offset(token)),
+            token = token,
+            name = pattern.get_token_const(token),
+            size = pattern.size,
+            mask = pattern.get_token_mask(token),
+            offset = pattern.get_token_offset(token),
+            width = pattern.get_token_bits(token),
+        );
+    }
+    let mut fits = String::new();
+    for field in fields.iter().filter(|field| field.is_enum(pattern)) {
+        fits += &format!(
+            "const _: () = <{} as ::bitrange::Field<{}>>::FITS;\n",
+            field.get_type(pattern),
+            pattern.get_token_width(field.char())
         );
     }
+    format!(
+        r#"
+#[allow(dead_code)]
+impl {struct_name} {{{consts}
+    {get_default_mask}
+    {get_default_value}
+}}
+{fits}"#,
+        struct_name = pattern.struct_name,
+        consts = consts,
+        get_default_mask = generate_default_mask(pattern, fields),
+        get_default_value = generate_default_value(pattern, fields),
+        fits = fits,
+    )
+}
+
+/// Generate the implementation of `bitrange::Field`, so the struct can be the type of a field of another bitrange struct
+/// with exactly as many bits, as in `f: flags as Ipv4Flags`.
+/// The `0` and `1` of the pattern are the `DEFAULT_MASK` and `DEFAULT_VALUE`, so the other struct checks them too
+fn generate_nested(pattern: &Pattern, options: &Options) -> String {
+    if pattern.get_width() > 128 {
+        return String::new();
+    }
+    let to_u128 = |value: &str| match pattern.is_array() {
+        true => format!("::bitrange::bytes::to_u128({})", value),
+        false => generate_cast(value, &pattern.size, "u128"),
+    };
+    let from_u128 = |value: &str| match pattern.is_array() {
+        true => format!("::bitrange::bytes::from_u128({})", value),
+        false => generate_cast(value, "u128", &pattern.size),
+    };
+    let fmt_bits = if options.derives("Debug") {
+        format!(
+            r#"
+    fn fmt_bits(bits: u128, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        ::core::fmt::Debug::fmt(&{struct_name}::from_bits_unchecked({bits}), f)
+    }}
+"#,
+            struct_name = pattern.struct_name,
+            bits = from_u128("bits"),
+        )
+    } else {
+        String::new()
+    };
 
     format!(
         r#"
-    /// Return the offset of a given character in a format.
-    /// This is the amount of least-significant bits in the proc_mask that are 0.
-    /// 
-    /// e.g. the offset for 0b0000_1100 would be 2
-    /// 
-    /// ```
-{examples}
-    /// ```
-    pub fn __bitrange_get_offset(c: &str) -> usize {{
-        match c {{
+impl ::bitrange::Field<{width}> for {struct_name} {{
+    type Output = {struct_name};
+
+    const DEFAULT_MASK: u128 = {mask};
+    const DEFAULT_VALUE: u128 = {value};
+
+    fn from_bits(bits: u128) -> {struct_name} {{
+        {struct_name}::from_bits_unchecked({from_bits})
+    }}
+
+    fn into_bits(self) -> u128 {{
+        {into_bits}
+    }}
+{fmt_bits}
+    fn fmt_cell(bits: u128, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        write!(f, "{{:0{width}b}}", bits)
+    }}
+}}
+
+impl {struct_name} {{
+    #[doc(hidden)]
+    #[allow(dead_code)]
+    pub const fn __bitrange_into_bits(self) -> u128 {{
+        {into_bits}
+    }}
+}}
+"#,
+        width = pattern.get_width(),
+        struct_name = pattern.struct_name,
+        mask = to_u128(&format!(
+            "{}::__bitrange_get_default_mask()",
+            pattern.struct_name
+        )),
+        value = to_u128(&format!(
+            "{}::__bitrange_get_default_value()",
+            pattern.struct_name
+        )),
+        from_bits = from_u128("bits"),
+        into_bits = to_u128("self.bits"),
+        fmt_bits = fmt_bits,
+    )
+}
+
+/// Generate the conversions of an enum with `#[derive(BitrangeEnum)]`,
+/// and implement `bitrange::Field` for the fields that can hold all the variants
+fn generate_enum(bitrange_enum: &BitrangeEnum) -> String {
+    let name = &bitrange_enum.name;
+    let width = bitrange_enum.get_width();
+
+    let mut case_statements = String::new();
+    let mut fmt_statements = String::new();
+    for (variant, discriminant) in &bitrange_enum.variants {
+        case_statements += &format!(
+            "            {} => Ok({}::{}),\n",
+            discriminant, name, variant
+        );
+        fmt_statements += &format!(
+            "            {} => f.write_str(\"{}\"),\n",
+            discriminant, variant
+        );
+    }
+    let mut str = format!(
+        r#"
+impl {name} {{
+    #[doc(hidden)]
+    #[allow(dead_code)]
+    pub fn __bitrange_from_bits(bits: u128) -> Result<{name}, ::bitrange::InvalidValue> {{
+        match bits {{
+{case_statements}
+            _ => Err(::bitrange::InvalidValue::new(bits)),
+        }}
+    }}
+
+    #[doc(hidden)]
+    #[allow(dead_code)]
+    pub const fn __bitrange_into_bits(self) -> u128 {{
+        self as u128
+    }}
+
+    #[doc(hidden)]
+    #[allow(dead_code)]
+    pub fn __bitrange_fmt_bits(bits: u128, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        match bits {{
+{fmt_statements}
+            _ => write!(f, "InvalidValue({{}})", bits),
+        }}
+    }}
+}}
+"#,
+        name = name,
+        case_statements = case_statements,
+        fmt_statements = fmt_statements,
+    );
+
+    let fallible = format!(
+        r#"
+    type Output = Result<{name}, ::bitrange::InvalidValue>;
+
+    fn from_bits(bits: u128) -> Result<{name}, ::bitrange::InvalidValue> {{
+        {name}::__bitrange_from_bits(bits)
+    }}
+
+    fn into_bits(self) -> u128 {{
+        self as u128
+    }}
+
+    fn fmt_bits(bits: u128, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        {name}::__bitrange_fmt_bits(bits, f)
+    }}
+"#,
+        name = name,
+    );
+    if !bitrange_enum.is_exhaustive() {
+        // A single implementation for every width, `FITS` rejects the fields that are too narrow
+        str += &format!(
+            r#"
+impl<const BITS: usize> ::bitrange::Field<BITS> for {name} {{
+    const FITS: () = assert!(BITS >= {width}, "`{name}` needs a field of at least {width} bits");
+{fallible}}}
+"#,
+            name = name,
+            width = width,
+            fallible = fallible,
+        );
+        return str;
+    }
+
+    // When every value of the field is a variant, the getter can not fail.
+    // The last variant is matched with `_` so the match is exhaustive.
+    // The `Output` of a wider field is a `Result`, which can not be chosen by a single generic implementation,
+    // so the wider fields get an implementation for every width
+    let (last, variants) = bitrange_enum.variants.split_last().unwrap();
+    let mut case_statements = String::new();
+    for (variant, discriminant) in variants {
+        case_statements += &format!("            {} => {}::{},\n", discriminant, name, variant);
+    }
+    str += &format!(
+        r#"
+impl ::bitrange::Field<{width}> for {name} {{
+    type Output = {name};
+
+    fn from_bits(bits: u128) -> {name} {{
+        match bits {{
 {case_statements}
-            _ => panic!("Invalid mask character (__bitrange_get_offset): {{:?}})", c),
+            _ => {name}::{last},
         }}
     }}
-    "#,
+
+    fn into_bits(self) -> u128 {{
+        self as u128
+    }}
+
+    fn fmt_bits(bits: u128, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        {name}::__bitrange_fmt_bits(bits, f)
+    }}
+}}
+"#,
+        width = width,
+        name = name,
         case_statements = case_statements,
-        examples = examples
+        last = last.0,
+    );
+    for width in width + 1..=128 {
+        str += &format!(
+            "\nimpl ::bitrange::Field<{}> for {} {{{}}}\n",
+            width, name, fallible
+        );
+    }
+    str
+}
+
+fn generate_field(pattern: &Pattern, field: &Field) -> String {
+    if field.get_type(pattern) == "bool" {
+        return generate_flag(pattern, field);
+    }
+    let runs = pattern.get_token_runs(field.char());
+    let ty = field.get_type(pattern);
+    let return_ty = field.get_return_type(pattern);
+    let bits = pattern.get_token_width(field.char());
+    let name = pattern.get_token_const(field.char());
+    let bits_type = pattern.get_token_bits_type(field.char());
+    // Conversions to an enum go through `bitrange::Field`, which can not be used in a const fn
+    let konst = if field.is_enum(pattern) { "" } else { "const " };
+
+    // Convert the bits of the field, which are in the size of the struct or in a `u128`, to the type of the field
+    let get_value = |value: &str| {
+        if field.is_enum(pattern) {
+            format!(
+                "<{} as ::bitrange::Field<{}>>::from_bits({})",
+                ty,
+                bits,
+                generate_cast(value, bits_type, "u128")
+            )
+        } else if let Some(width) = field.get_signed_width(pattern) {
+            generate_sign_extend(&generate_cast(value, bits_type, &ty), width, bits)
+        } else {
+            generate_cast(value, bits_type, &ty)
+        }
+    };
+    let convert = if field.is_enum(pattern) {
+        format!(
+            "\n        let value = {};",
+            generate_cast(
+                &format!("<{} as ::bitrange::Field<{}>>::into_bits(value)", ty, bits),
+                "u128",
+                bits_type
+            )
+        )
+    } else if ty == bits_type {
+        String::new()
+    } else {
+        format!("\n        let value = value as {};", bits_type)
+    };
+
+    // Fields with a single range of bits are a simple mask and shift,
+    // fields that are spread over the pattern or over the bytes of a `[u8; N]` are gathered and scattered one range at a time
+    let (get, set) =
+        if runs.len() <= 1 && runs.iter().all(|run| run.field_offset == 0) && !pattern.is_array() {
+            (
+                format!(
+                    "\n        {}",
+                    get_value(&format!(
+                        "(self.bits & {struct_name}::{name}_MASK) >> {struct_name}::{name}_OFFSET",
+                        struct_name = pattern.struct_name,
+                        name = name,
+                    ))
+                ),
+                format!(
+                    r#"{convert}
+        self.bits &= !{struct_name}::{name}_MASK;
+        self.bits |= (value << {struct_name}::{name}_OFFSET) & {struct_name}::{name}_MASK;"#,
+                    struct_name = pattern.struct_name,
+                    name = name,
+                    convert = convert,
+                ),
+            )
+        } else {
+            (
+                format!(
+                    r#"
+        let bits = self.bits;
+        {value}"#,
+                    value = get_value(&generate_gather(pattern, &runs, "bits", bits_type)),
+                ),
+                format!(
+                    "{}{}",
+                    convert,
+                    generate_assign(
+                        pattern,
+                        &format!("{}::{}_MASK", pattern.struct_name, name),
+                        &generate_scatter(pattern, &runs, "value", bits_type)
+                    )
+                ),
+            )
+        };
+
+    let mut str = format!(
+        r#"
+    pub {konst}fn {getter}(&self) -> {return_ty} {{{get}
+    }}
+"#,
+        konst = konst,
+        getter = field.getter,
+        return_ty = return_ty,
+        get = get,
+    );
+    if let Some(setter) = &field.setter {
+        if let Some(names) = field.get_setter_names(pattern) {
+            str += &generate_setters(pattern, field, setter, names, &set);
+        } else {
+            str += &format!(
+                r#"
+    pub {konst}fn {setter}(&mut self, value: {ty}) -> &mut Self {{{set}
+        self
+    }}
+"#,
+                konst = konst,
+                setter = setter,
+                ty = ty,
+                set = set,
+            );
+        }
+    }
+    str
+}
+
+/// Generate the setters of a numeric field: `set_x`, `try_set_x`, `set_x_wrapping` and `set_x_saturating`.
+/// Values that do not fit in the field are truncated by `set_x_wrapping`, every other setter builds on that.
+fn generate_setters(
+    pattern: &Pattern,
+    field: &Field,
+    setter: &Ident,
+    (try_setter, wrapping, saturating): (Ident, Ident, Ident),
+    set: &str,
+) -> String {
+    // `Ord::clamp` and `Ord::min` are not const fns
+    let saturate = match get_range(pattern, field) {
+        Some((Some(min), max)) => format!(
+            "if value < {min} {{ {min} }} else if value > {max} {{ {max} }} else {{ value }}",
+            min = min,
+            max = max
+        ),
+        Some((None, max)) => format!("if value > {max} {{ {max} }} else {{ value }}", max = max),
+        None => "value".to_string(),
+    };
+    let try_check = match generate_fits(pattern, field, "value") {
+        Some(fits) => format!(
+            r#"
+        if !({fits}) {{
+            return Err(::bitrange::FieldOverflow::new("{getter}", {bits}));
+        }}"#,
+            fits = fits,
+            bits = pattern.get_token_width(field.char()),
+            getter = field.getter,
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        r#"
+    pub const fn {setter}(&mut self, value: {ty}) -> &mut Self {{{check}
+        self.{wrapping}(value)
+    }}
+
+    pub const fn {try_setter}(&mut self, value: {ty}) -> Result<&mut Self, ::bitrange::FieldOverflow> {{{try_check}
+        Ok(self.{wrapping}(value))
+    }}
+
+    pub const fn {wrapping}(&mut self, value: {ty}) -> &mut Self {{{set}
+        self
+    }}
+
+    pub const fn {saturating}(&mut self, value: {ty}) -> &mut Self {{
+        self.{wrapping}({saturate})
+    }}
+"#,
+        setter = setter,
+        try_setter = try_setter,
+        wrapping = wrapping,
+        saturating = saturating,
+        ty = field.get_type(pattern),
+        check = generate_check(pattern, field, "value", "debug_assert"),
+        try_check = try_check,
+        set = set,
+        saturate = saturate,
+    )
+}
+
+/// Generate an expression that moves the value of `field` in the variable `value` to the bits of the field
+fn generate_value_bits(pattern: &Pattern, field: &Field, value: &str) -> String {
+    let ty = field.get_type(pattern);
+    let runs = pattern.get_token_runs(field.char());
+    let bits_type = pattern.get_token_bits_type(field.char());
+    if field.is_enum(pattern) {
+        // Enums are not `Copy`, so they are converted once before they are spread over the runs.
+        // `Field::into_bits` is not a const fn, so this uses the `__bitrange_into_bits` of the enum or bitrange struct
+        return format!(
+            "{{ let bits = {}; {} }}",
+            generate_cast(
+                &format!("<{}>::__bitrange_into_bits({})", ty, value),
+                "u128",
+                bits_type
+            ),
+            generate_scatter(pattern, &runs, "bits", bits_type)
+        );
+    }
+    let mut value = generate_cast(value, &ty, bits_type);
+    if ty != bits_type {
+        value = format!("({})", value);
+    }
+    generate_scatter(pattern, &runs, &value, bits_type)
+}
+
+/// Generate a positional `const fn new(first, second, ...)` that takes the value of every field, in the order they are mapped
+fn generate_new(pattern: &Pattern, fields: &[&Field]) -> String {
+    let mut args = Vec::new();
+    let mut checks = String::new();
+    let mut bits = pattern.get_default_value();
+    for field in fields {
+        let value = field.getter.to_string();
+        args.push(format!("{}: {}", value, field.get_type(pattern)));
+        checks += &generate_check(pattern, field, &value, "assert");
+        let value_bits = generate_value_bits(pattern, field, &value);
+        if pattern.is_array() {
+            bits = generate_or(pattern, &bits, &value_bits);
+        } else {
+            bits += &format!("\n                | {}", value_bits);
+        }
+    }
+
+    format!(
+        r#"
+    /// Create a new value from the value of every field, in the order they are mapped.
+    /// The bits that are always `0` or `1` are set to their default value
+    #[allow(dead_code, clippy::too_many_arguments)]
+    pub const fn new({args}) -> {struct_name} {{{checks}
+        {struct_name} {{
+            bits: {bits}
+        }}
+    }}
+"#,
+        args = args.join(", "),
+        checks = checks,
+        struct_name = pattern.struct_name,
+        bits = bits,
+    )
+}
+
+/// Generate `Test::builder()`, which can set any of the fields and starts from the default value,
+/// and `Test::strict_builder()`, which can only be built once every field has been given a value
+fn generate_builders(pattern: &Pattern, fields: &[&Field]) -> String {
+    let struct_name = &pattern.struct_name;
+    let states = (0..fields.len())
+        .map(|i| format!("F{}", i))
+        .collect::<Vec<_>>();
+    let state_with = |i: usize, state: &str| {
+        let mut states = states.clone();
+        states[i] = state.to_string();
+        states.join(", ")
+    };
+
+    let mut methods = String::new();
+    let mut strict_methods = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        methods += &format!(
+            r#"
+    pub const fn {getter}(mut self, value: {ty}) -> Self {{{check}{assign}
+        self
+    }}
+"#,
+            getter = field.getter,
+            ty = field.get_type(pattern),
+            check = generate_check(pattern, field, "value", "assert"),
+            assign = generate_assign(
+                pattern,
+                &format!(
+                    "{}::{}_MASK",
+                    struct_name,
+                    pattern.get_token_const(field.char())
+                ),
+                &generate_value_bits(pattern, field, "value")
+            ),
+        );
+        strict_methods += &format!(
+            r#"
+    pub const fn {getter}(self, value: {ty}) -> {struct_name}StrictBuilder<{state}> {{
+        {struct_name}StrictBuilder {{
+            bits: {struct_name}Builder {{ bits: self.bits }}.{getter}(value).bits,
+            state: ::core::marker::PhantomData,
+        }}
+    }}
+"#,
+            getter = field.getter,
+            ty = field.get_type(pattern),
+            struct_name = struct_name,
+            state = state_with(i, "::bitrange::Set"),
+        );
+    }
+
+    let all = |state: &str| vec![state; fields.len()].join(", ");
+    format!(
+        r#"
+impl {struct_name} {{
+    /// Create a builder that starts from the default value, and can set any of the fields
+    #[allow(dead_code)]
+    pub const fn builder() -> {struct_name}Builder {{
+        {struct_name}Builder {{
+            bits: {struct_name}::__bitrange_get_default_value()
+        }}
+    }}
+
+    /// Create a builder that can only be built once every field has been given a value
+    #[allow(dead_code)]
+    pub const fn strict_builder() -> {struct_name}StrictBuilder<{unset}> {{
+        {struct_name}StrictBuilder {{
+            bits: {struct_name}::__bitrange_get_default_value(),
+            state: ::core::marker::PhantomData,
+        }}
+    }}
+}}
+
+/// A builder for [`{struct_name}`], created with [`{struct_name}::builder`]
+#[allow(dead_code)]
+pub struct {struct_name}Builder {{
+    bits: {size}
+}}
+
+#[allow(dead_code, clippy::manual_range_contains)]
+impl {struct_name}Builder {{{methods}
+    pub const fn build(self) -> {struct_name} {{
+        {struct_name} {{
+            bits: self.bits
+        }}
+    }}
+}}
+
+/// A builder for [`{struct_name}`] that keeps track of the fields that are set, created with [`{struct_name}::strict_builder`]
+#[allow(dead_code)]
+pub struct {struct_name}StrictBuilder<{states}> {{
+    bits: {size},
+    state: ::core::marker::PhantomData<({states_tuple})>,
+}}
+
+#[allow(dead_code)]
+impl<{states}> {struct_name}StrictBuilder<{states}> {{{strict_methods}}}
+
+#[allow(dead_code)]
+impl {struct_name}StrictBuilder<{set}> {{
+    pub const fn build(self) -> {struct_name} {{
+        {struct_name} {{
+            bits: self.bits
+        }}
+    }}
+}}
+"#,
+        struct_name = struct_name,
+        size = pattern.size,
+        methods = methods,
+        strict_methods = strict_methods,
+        states = states.join(", "),
+        states_tuple = states.iter().map(|s| format!("{},", s)).collect::<String>(),
+        unset = all("::bitrange::Unset"),
+        set = all("::bitrange::Set"),
     )
 }
 
-fn generate_default_mask(pattern: &Pattern) -> String {
+/// Generate an expression that checks if `value` fits in a numeric field, if not every value of its type fits.
+/// The bits of the value below the lowest bit in the order of the field have to be 0.
+/// This is used in const fns, so it can not use `RangeInclusive::contains`
+fn generate_fits(pattern: &Pattern, field: &Field, value: &str) -> Option<String> {
+    if field.is_enum(pattern) {
+        return None;
+    }
+    let zeros = pattern.get_token_zeros(field.char());
+    let range = match get_range(pattern, field) {
+        Some((Some(min), max)) => Some(format!(
+            "{value} >= {min} && {value} <= {max}",
+            value = value,
+            min = min,
+            max = max
+        )),
+        Some((None, max)) => Some(format!("{} <= {}", value, max)),
+        None => None,
+    };
+    match (range, zeros) {
+        (range, 0) => range,
+        (Some(range), zeros) => Some(format!(
+            "{} && {} & {:#b} == 0",
+            range,
+            value,
+            (1u128 << zeros) - 1
+        )),
+        (None, zeros) => Some(format!("{} & {:#b} == 0", value, (1u128 << zeros) - 1)),
+    }
+}
+
+/// Generate an `assert!` or `debug_assert!` that checks if `value` fits in a numeric field.
+/// `new` and the builders always check the value, so a value that does not fit is a compile error in a const,
+/// `set_x` only checks it in debug builds, like the arithmetic of integers
+fn generate_check(pattern: &Pattern, field: &Field, value: &str, assert: &str) -> String {
+    match generate_fits(pattern, field, value) {
+        Some(fits) => format!(
+            r#"
+        {assert}!(
+            {fits},
+            "{struct_name}: value does not fit in the {bits} bits of field `{getter}`"
+        );"#,
+            assert = assert,
+            fits = fits,
+            struct_name = pattern.struct_name,
+            bits = pattern.get_token_width(field.char()),
+            getter = field.getter,
+        ),
+        None => String::new(),
+    }
+}
+
+/// Returns the smallest and largest value that fit in a numeric field, if that is smaller than the range of its type.
+/// The smallest value is `None` for unsigned fields, where it is always 0
+fn get_range(pattern: &Pattern, field: &Field) -> Option<(Option<String>, String)> {
+    let ty = field.get_type(pattern);
+    let bits = pattern.get_token_width(field.char());
+    if field.is_enum(pattern) {
+        return None;
+    }
+    match (
+        field.get_signed_width(pattern),
+        Pattern::get_size_width(&ty),
+    ) {
+        (Some(width), _) if width > bits => Some((
+            Some(format!("{}::MIN >> {}", ty, width - bits)),
+            format!("{}::MAX >> {}", ty, width - bits),
+        )),
+        (None, Some(width)) if width > bits => {
+            Some((None, format!("{}::MAX >> {}", ty, width - bits)))
+        }
+        _ => None,
+    }
+}
+
+/// Generate the getter and setters of a field with a single bit, that is represented as a `bool`
+fn generate_flag(pattern: &Pattern, field: &Field) -> String {
+    let mut bits = "self.bits".to_string();
+    let mut mask = format!(
+        "{}::{}_MASK",
+        pattern.struct_name,
+        pattern.get_token_const(field.char())
+    );
+    // The bit of a flag in a `[u8; N]` is in a single byte
+    if pattern.is_array() {
+        if let Some((index, run)) =
+            get_byte_runs(pattern, &pattern.get_token_runs(field.char())).pop()
+        {
+            bits = format!("self.bits[{}]", index);
+            mask = format!("{:#010b}", 1u8 << run.offset);
+        }
+    }
+    let mut str = format!(
+        r#"
+    pub const fn {getter}(&self) -> bool {{
+        {bits} & {mask} != 0
+    }}
+"#,
+        getter = field.getter,
+        bits = bits,
+        mask = mask,
+    );
+    let (setter, (toggle, set_on, clear)) = match (&field.setter, field.get_flag_names(pattern)) {
+        (Some(setter), Some(names)) => (setter, names),
+        _ => return str,
+    };
+    str += &format!(
+        r#"
+    pub const fn {setter}(&mut self, value: bool) -> &mut Self {{
+        if value {{
+            {bits} |= {mask};
+        }} else {{
+            {bits} &= !{mask};
+        }}
+        self
+    }}
+
+    pub const fn {toggle}(&mut self) -> &mut Self {{
+        {bits} ^= {mask};
+        self
+    }}
+
+    pub const fn {set_on}(&mut self) -> &mut Self {{
+        self.{setter}(true)
+    }}
+
+    pub const fn {clear}(&mut self) -> &mut Self {{
+        self.{setter}(false)
+    }}
+"#,
+        setter = setter,
+        toggle = toggle,
+        set_on = set_on,
+        clear = clear,
+        bits = bits,
+        mask = mask,
+    );
+    str
+}
+
+/// Cast `value` from the type `from` to the type `to`, if these types are different
+fn generate_cast(value: &str, from: &str, to: &str) -> String {
+    if from == to {
+        value.to_string()
+    } else if value.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        format!("{} as {}", value, to)
+    } else {
+        format!("({}) as {}", value, to)
+    }
+}
+
+/// Sign-extend the lowest `bits` bits of `value`, which is a signed integer of `width` bits
+fn generate_sign_extend(value: &str, width: usize, bits: usize) -> String {
+    if width == bits {
+        value.to_string()
+    } else {
+        format!("(({}) << {}) >> {}", value, width - bits, width - bits)
+    }
+}
+
+/// Generate an expression that packs the bits of `runs` in `bits` into the least significant bits, like `pext`.
+/// The result is of the type `ty`, which is `Pattern::get_bits_type` or a `u128` for a value that does not fit in it
+fn generate_gather(pattern: &Pattern, runs: &[Run], bits: &str, ty: &str) -> String {
+    if pattern.is_array() {
+        return get_byte_runs(pattern, runs)
+            .iter()
+            .map(|(index, run)| {
+                let mut str = format!("{}[{}]", bits, index);
+                if run.offset > 0 {
+                    str = format!("({} >> {})", str, run.offset);
+                }
+                str = format!("(({} & {}) as u128)", str, run.get_mask());
+                if run.field_offset > 0 {
+                    str = format!("({} << {})", str, run.field_offset);
+                }
+                str
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+    }
+    runs.iter()
+        .map(|run| {
+            let mut str = bits.to_string();
+            if run.offset > 0 {
+                str = format!("({} >> {})", str, run.offset);
+            }
+            str = format!("({} & {})", str, run.get_mask());
+            if ty != pattern.get_bits_type() {
+                str = format!("({} as {})", str, ty);
+            }
+            if run.field_offset > 0 {
+                str = format!("({} << {})", str, run.field_offset);
+            }
+            str
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Generate an expression that spreads the least significant bits of `value` over `runs`, like `pdep`.
+/// `value` is of the type `ty`, which is `Pattern::get_bits_type` or a `u128` for a value that does not fit in it
+fn generate_scatter(pattern: &Pattern, runs: &[Run], value: &str, ty: &str) -> String {
+    if pattern.is_array() {
+        let mut bytes = vec![Vec::new(); pattern.get_bytes()];
+        for (index, run) in get_byte_runs(pattern, runs) {
+            let mut str = value.to_string();
+            if run.field_offset > 0 {
+                str = format!("({} >> {})", str, run.field_offset);
+            }
+            str = format!("(({} & {}) as u8)", str, run.get_mask());
+            if run.offset > 0 {
+                str = format!("({} << {})", str, run.offset);
+            }
+            bytes[index].push(str);
+        }
+        let bytes = bytes
+            .iter()
+            .map(|parts| match parts.len() {
+                0 => "0".to_string(),
+                _ => parts.join(" | "),
+            })
+            .collect::<Vec<_>>();
+        return format!("[{}]", bytes.join(", "));
+    }
+    runs.iter()
+        .map(|run| {
+            let mut str = value.to_string();
+            if run.field_offset > 0 {
+                str = format!("({} >> {})", str, run.field_offset);
+            }
+            str = format!("({} & {})", str, run.get_mask());
+            if ty != pattern.get_bits_type() {
+                str = format!("({} as {})", str, pattern.get_bits_type());
+            }
+            if run.offset > 0 {
+                str = format!("({} << {})", str, run.offset);
+            }
+            str
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Split `runs` in the parts that are in a single byte of a `[u8; N]`.
+/// Returns the index of the byte, and the run with the offset of its least significant bit in that byte
+fn get_byte_runs(pattern: &Pattern, runs: &[Run]) -> Vec<(usize, Run)> {
+    let bytes = pattern.get_bytes();
+    let mut byte_runs = Vec::new();
+    for run in runs {
+        let mut offset = run.offset;
+        while offset < run.offset + run.width {
+            let width = (8 - offset % 8).min(run.offset + run.width - offset);
+            byte_runs.push((
+                bytes - 1 - offset / 8,
+                Run {
+                    offset: offset % 8,
+                    width,
+                    field_offset: run.field_offset + offset - run.offset,
+                },
+            ));
+            offset += width;
+        }
+    }
+    byte_runs
+}
+
+/// Generate `a & b` for two values of the internal integer
+fn generate_and(pattern: &Pattern, a: &str, b: &str) -> String {
+    if pattern.is_array() {
+        format!("::bitrange::bytes::and({}, {})", a, b)
+    } else {
+        format!("({} & {})", a, b)
+    }
+}
+
+/// Generate `a | b` for two values of the internal integer
+fn generate_or(pattern: &Pattern, a: &str, b: &str) -> String {
+    if pattern.is_array() {
+        format!("::bitrange::bytes::or({}, {})", a, b)
+    } else {
+        format!("({} | {})", a, b)
+    }
+}
+
+/// Generate `a ^ b` for two values of the internal integer
+fn generate_xor(pattern: &Pattern, a: &str, b: &str) -> String {
+    if pattern.is_array() {
+        format!("::bitrange::bytes::xor({}, {})", a, b)
+    } else {
+        format!("({} ^ {})", a, b)
+    }
+}
+
+/// Generate `!a` for a value of the internal integer
+fn generate_not(pattern: &Pattern, a: &str) -> String {
+    if pattern.is_array() {
+        format!("::bitrange::bytes::not({})", a)
+    } else {
+        format!("!{}", a)
+    }
+}
+
+/// Generate `a == b` for two values of the internal integer, which can not use `PartialEq` in a const fn
+fn generate_eq(pattern: &Pattern, a: &str, b: &str) -> String {
+    if pattern.is_array() {
+        format!("::bitrange::bytes::eq({}, {})", a, b)
+    } else {
+        format!("{} == {}", a, b)
+    }
+}
+
+/// Generate the statements that replace the bits in `mask` of `self.bits` with `value`
+fn generate_assign(pattern: &Pattern, mask: &str, value: &str) -> String {
+    if pattern.is_array() {
+        format!(
+            "\n        self.bits = {};",
+            generate_or(
+                pattern,
+                &generate_and(pattern, "self.bits", &generate_not(pattern, mask)),
+                value
+            )
+        )
+    } else {
+        format!(
+            "\n        self.bits &= !{};\n        self.bits |= {};",
+            mask, value
+        )
+    }
+}
+
+fn generate_default_mask(pattern: &Pattern, fields: &[&Field]) -> String {
     format!(
         r#"
     /// Return the default mask of a format.
@@ -108,17 +1644,18 @@ fn generate_default_mask(pattern: &Pattern) -> String {
     /// ```
     /// assert_eq!({result}, {struct_name}::__bitrange_get_default_mask());
     /// ```
-    pub fn __bitrange_get_default_mask() -> {size} {{
+    pub const fn __bitrange_get_default_mask() -> {size} {{
         {result}
     }}
 "#,
         struct_name = pattern.struct_name,
-        result = pattern.get_default_mask(),
+        result =
+            generate_nested_defaults(pattern, fields, pattern.get_default_mask(), "DEFAULT_MASK"),
         size = pattern.size,
     )
 }
 
-fn generate_default_value(pattern: &Pattern) -> String {
+fn generate_default_value(pattern: &Pattern, fields: &[&Field]) -> String {
     format!(
         r#"
     /// Returns the default value of a format
@@ -127,12 +1664,48 @@ fn generate_default_value(pattern: &Pattern) -> String {
     /// ```
     /// assert_eq!({result}, {struct_name}::__bitrange_get_default_value());
     /// ```
-    pub fn __bitrange_get_default_value() -> {size} {{
+    pub const fn __bitrange_get_default_value() -> {size} {{
         {result}
     }}
 "#,
-        result = pattern.get_default_value(),
+        result = generate_nested_defaults(
+            pattern,
+            fields,
+            pattern.get_default_value(),
+            "DEFAULT_VALUE"
+        ),
         struct_name = pattern.struct_name,
         size = pattern.size,
     )
 }
+
+/// Add the `DEFAULT_MASK` or `DEFAULT_VALUE` of the type of every field that implements `bitrange::Field`
+/// to the default mask or value of the pattern, so the `0` and `1` of a nested bitrange struct are checked as well
+fn generate_nested_defaults(
+    pattern: &Pattern,
+    fields: &[&Field],
+    bits: String,
+    name: &str,
+) -> String {
+    let mut bits = bits;
+    for field in fields.iter().filter(|field| field.is_enum(pattern)) {
+        let value = generate_cast(
+            &format!(
+                "<{} as ::bitrange::Field<{}>>::{}",
+                field.get_type(pattern),
+                pattern.get_token_width(field.char()),
+                name
+            ),
+            "u128",
+            pattern.get_token_bits_type(field.char()),
+        );
+        let value = generate_scatter(
+            pattern,
+            &pattern.get_token_runs(field.char()),
+            &value,
+            pattern.get_token_bits_type(field.char()),
+        );
+        bits = generate_or(pattern, &bits, &value);
+    }
+    bits
+}
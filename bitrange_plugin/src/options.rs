@@ -0,0 +1,241 @@
+use quote::ToTokens;
+use syn::{parse_quote, Attribute, Ident, Lit, Meta, NestedMeta};
+
+/// The traits that are implemented for a bitrange struct, unless `#[bitrange(derive(...))]` is given
+pub const DERIVES: [&str; 11] = [
+    "Clone",
+    "Copy",
+    "PartialEq",
+    "Eq",
+    "Hash",
+    "Debug",
+    "Display",
+    "Binary",
+    "LowerHex",
+    "UpperHex",
+    "Octal",
+];
+
+/// The byte order of `TryFrom<&[u8]>` and `From<Struct> for [u8; N]`, given with `#[bitrange(byte_order = "...")]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    /// The most significant byte first, like the pattern and network protocols
+    #[default]
+    Big,
+    Little,
+    Native,
+}
+
+impl ByteOrder {
+    /// Returns the suffix of the conversion functions of this byte order, e.g. `be` for `to_be_bytes`
+    pub fn suffix(self) -> &'static str {
+        match self {
+            ByteOrder::Big => "be",
+            ByteOrder::Little => "le",
+            ByteOrder::Native => "ne",
+        }
+    }
+}
+
+/// The numbering of the bits in the constants, errors and diagrams, given with `#[bitrange(bit_numbering = "...")]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitNumbering {
+    /// Bit 0 is the least significant bit
+    #[default]
+    Lsb0,
+    /// Bit 0 is the most significant bit, like in RFC 791
+    Msb0,
+}
+
+impl BitNumbering {
+    /// Returns the path of the matching `bitrange::BitNumbering`
+    pub fn path(self) -> &'static str {
+        match self {
+            BitNumbering::Lsb0 => "::bitrange::BitNumbering::Lsb0",
+            BitNumbering::Msb0 => "::bitrange::BitNumbering::Msb0",
+        }
+    }
+
+    /// Returns the index of a bit in this numbering, where `bit` is counted from the least significant bit
+    /// and `width` is the amount of bits of the pattern
+    pub fn index(self, bit: usize, width: usize) -> usize {
+        match self {
+            BitNumbering::Lsb0 => bit,
+            BitNumbering::Msb0 => width - 1 - bit,
+        }
+    }
+}
+
+/// The options of a bitrange struct, given with `#[bitrange(...)]`
+///
+/// ```ignore
+/// bitrange! {
+///     #[bitrange(short, derive(Clone, Copy), byte_order = "little", bit_numbering = "msb0")]
+///     Test: u8,
+///     aaaa,
+///     a: first
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Allow patterns that have less bits than the size.
+    /// The pattern is aligned to the least significant bits, and the remaining bits are unmapped.
+    pub short: bool,
+    /// The traits that are implemented for the struct, one of `DERIVES`.
+    /// If this is `None`, all of `DERIVES` are implemented
+    pub derive: Option<Vec<Ident>>,
+    /// The byte order of the conversions from and to a slice of bytes, big-endian by default
+    pub byte_order: ByteOrder,
+    /// The numbering of the bits, where bit 0 is the least significant bit by default
+    pub bit_numbering: BitNumbering,
+}
+
+impl Options {
+    /// Returns true if the given attribute is a `#[bitrange(...)]` attribute
+    pub fn is_option(attr: &Attribute) -> bool {
+        attr.path.is_ident("bitrange")
+    }
+
+    /// Parse all the `#[bitrange(...)]` attributes in the given list
+    pub fn from_attrs(struct_name: &str, attrs: &[Attribute]) -> syn::Result<Options> {
+        Options::default().with_attrs(struct_name, attrs)
+    }
+
+    /// Parse all the `#[bitrange(...)]` attributes in the given list, on top of the given options
+    pub fn with_attrs(self, struct_name: &str, attrs: &[Attribute]) -> syn::Result<Options> {
+        let mut options = self;
+        for attr in attrs.iter().filter(|a| Options::is_option(a)) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        format!("{}: expected #[bitrange(...)]", struct_name),
+                    ))
+                }
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("short") => {
+                        options.short = true;
+                    }
+                    NestedMeta::Meta(Meta::List(ref list)) if list.path.is_ident("derive") => {
+                        let derive = options.derive.get_or_insert_with(Vec::new);
+                        for nested in &list.nested {
+                            derive.push(Options::get_derive(struct_name, nested)?);
+                        }
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref name_value))
+                        if name_value.path.is_ident("byte_order") =>
+                    {
+                        options.byte_order = match name_value.lit {
+                            Lit::Str(ref lit) if lit.value() == "big" => ByteOrder::Big,
+                            Lit::Str(ref lit) if lit.value() == "little" => ByteOrder::Little,
+                            Lit::Str(ref lit) if lit.value() == "native" => ByteOrder::Native,
+                            ref lit => {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    format!(
+                                        "{}: invalid byte order {}, expected \"big\", \"little\" or \"native\"",
+                                        struct_name,
+                                        lit.to_token_stream()
+                                    ),
+                                ))
+                            }
+                        };
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref name_value))
+                        if name_value.path.is_ident("bit_numbering") =>
+                    {
+                        options.bit_numbering = match name_value.lit {
+                            Lit::Str(ref lit) if lit.value() == "lsb0" => BitNumbering::Lsb0,
+                            Lit::Str(ref lit) if lit.value() == "msb0" => BitNumbering::Msb0,
+                            ref lit => {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    format!(
+                                        "{}: invalid bit numbering {}, expected \"lsb0\" or \"msb0\"",
+                                        struct_name,
+                                        lit.to_token_stream()
+                                    ),
+                                ))
+                            }
+                        };
+                    }
+                    nested => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            format!(
+                                "{}: unknown option in #[bitrange(...)], expected `short`, `derive(...)`, `byte_order = \"...\"` or `bit_numbering = \"...\"`",
+                                struct_name
+                            ),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /// Returns true if the given trait should be implemented for the struct
+    pub fn derives(&self, name: &str) -> bool {
+        if !DERIVES.contains(&name) {
+            return false;
+        }
+        match self.derive {
+            Some(ref derive) => derive.iter().any(|ident| ident == name),
+            None => true,
+        }
+    }
+
+    /// Remove the traits that are implemented by bitrange from the `#[derive(...)]` attributes in the given list,
+    /// so `#[derive(Debug)]` on a bitrange struct does not conflict with the generated `Debug`
+    pub fn strip_derives(&self, attrs: Vec<Attribute>) -> Vec<Attribute> {
+        let mut result = Vec::new();
+        for attr in attrs {
+            let list = match attr.parse_meta() {
+                Ok(Meta::List(ref list)) if list.path.is_ident("derive") => list.clone(),
+                _ => {
+                    result.push(attr);
+                    continue;
+                }
+            };
+            let rest = list
+                .nested
+                .iter()
+                .filter(|nested| match nested {
+                    NestedMeta::Meta(Meta::Path(ref path)) => match path.segments.last() {
+                        Some(segment) => !self.derives(&segment.ident.to_string()),
+                        None => true,
+                    },
+                    _ => true,
+                })
+                .collect::<Vec<_>>();
+            if !rest.is_empty() {
+                result.push(parse_quote!(#[derive(#(#rest),*)]));
+            }
+        }
+        result
+    }
+
+    fn get_derive(struct_name: &str, nested: &NestedMeta) -> syn::Result<Ident> {
+        match nested {
+            NestedMeta::Meta(Meta::Path(ref path)) => match path.get_ident() {
+                Some(ident) if DERIVES.iter().any(|name| ident == name) => Ok(ident.clone()),
+                _ => Err(syn::Error::new_spanned(
+                    path,
+                    format!(
+                        "{}: can not derive {}, expected one of {}",
+                        struct_name,
+                        path.to_token_stream(),
+                        DERIVES.join(", ")
+                    ),
+                )),
+            },
+            nested => Err(syn::Error::new_spanned(
+                nested,
+                format!("{}: expected one of {}", struct_name, DERIVES.join(", ")),
+            )),
+        }
+    }
+}
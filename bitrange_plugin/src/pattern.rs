@@ -1,106 +1,440 @@
-use proc_macro::TokenStream;
-use proc_macro2::TokenTree;
-use std::collections::HashSet;
-use syn;
+use crate::options::{ByteOrder, Options};
+use proc_macro2::{Delimiter, Span, TokenStream as TokenStream2, TokenTree};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
 pub struct Pattern {
     pub struct_name: String,
+    /// The type of the internal integer. For a width like `u24`, this is the smallest integer type that can hold it
     pub size: String,
+    /// The amount of bits of the internal integer that hold the pattern, e.g. 24 for `u24`
+    pub width: usize,
     pub trimmed_pattern: String,
     pub original_pattern: String,
     pub tokens: HashSet<char>,
+    /// The span of every character in `original_pattern`
+    pub spans: Vec<Span>,
+    /// The prefix of the associated constants of a character, if it is not the character itself
+    pub const_names: HashMap<char, String>,
+    /// The index in the value of every bit of a character, from the first to the last bit in the pattern,
+    /// if it is not the order of the pattern
+    pub bit_orders: HashMap<char, Vec<usize>>,
 }
 
-#[derive(Default)]
-struct Parsed {
-    struct_name: String,
-    original_pattern: String,
-    trimmed_pattern: String,
-    size: String,
+/// A range of consecutive bits in a pattern that are mapped to the same field
+#[derive(Debug)]
+pub struct Run {
+    /// The index of the least significant bit of this run in the internal integer
+    pub offset: usize,
+    /// The amount of bits in this run
+    pub width: usize,
+    /// The index of the least significant bit of this run in the value of the field
+    pub field_offset: usize,
+}
+
+impl Run {
+    /// Returns a mask of `width` bits, e.g. `0b111` for a run of 3 bits
+    pub fn get_mask(&self) -> String {
+        format!("{:#b}", u128::MAX >> (128 - self.width))
+    }
 }
 
 impl Pattern {
-    pub fn from_stream(stream: TokenStream) -> Result<Pattern, String> {
-        let parsed = Pattern::parse(stream)?;
-        let tokens = parsed.trimmed_pattern.chars().collect::<HashSet<_>>();
+    /// Create a new pattern from the given tokens.
+    /// The size is the type of the internal integer, e.g. `u32` or `"u32"`, or a width like `u24`.
+    /// The pattern can either be a string literal (`"aaaa_bbbb"`) or a list of tokens (`aaaa_bbbb`)
+    pub fn new(
+        struct_name: String,
+        size: TokenStream2,
+        source: TokenStream2,
+        options: &Options,
+    ) -> syn::Result<Pattern> {
+        let (mut size_string, _) = Pattern::get_string(&size);
+        if let Some(bytes) = Pattern::get_array_length(&size_string) {
+            size_string = format!("[u8; {}]", bytes);
+            if options.byte_order != ByteOrder::Big {
+                return Err(syn::Error::new_spanned(
+                    &size,
+                    format!(
+                        "{}: a byte order can not be given for {}, the first byte always holds the first bits of the pattern",
+                        struct_name, size_string
+                    ),
+                ));
+            }
+        }
+        let width = Pattern::get_storage_width(&size_string)
+            .or_else(|| Pattern::get_odd_width(&size_string))
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    &size,
+                    format!(
+                        "{}: invalid size {:?}, expected one of u8, u16, u32, u64, u128, a width like u24, or [u8; N]",
+                        struct_name, size_string
+                    ),
+                )
+            })?;
+
+        let (original_pattern, spans) = Pattern::get_string(&source);
+        let (original_pattern, spans) = Pattern::expand(&struct_name, &original_pattern, &spans)?;
+        if original_pattern.is_empty() {
+            return Err(syn::Error::new(
+                source
+                    .clone()
+                    .into_iter()
+                    .next()
+                    .map(|t| t.span())
+                    .unwrap_or_else(Span::call_site),
+                format!("{}: pattern is empty", struct_name),
+            ));
+        }
+        for (c, span) in original_pattern.chars().zip(spans.iter()) {
+            if !c.is_alphanumeric() && c != '_' {
+                return Err(syn::Error::new(
+                    *span,
+                    format!(
+                        "{}: invalid character {:?} in pattern {:?}, only alphanumeric characters and '_' are allowed",
+                        struct_name, c, original_pattern
+                    ),
+                ));
+            }
+            // The characters of the pattern are mapped with `c: name`, which can not start with a digit
+            if c.is_ascii_digit() && c != '0' && c != '1' {
+                return Err(syn::Error::new(
+                    *span,
+                    format!(
+                        "{}: invalid character {:?} in pattern {:?}, only 0 and 1 can be digits, use a letter for a field",
+                        struct_name, c, original_pattern
+                    ),
+                ));
+            }
+        }
+        let trimmed_pattern = Pattern::trim(&original_pattern);
+        let bits = trimmed_pattern.chars().count();
+        if bits > width || (bits < width && !options.short) {
+            let hint = if bits < width {
+                ", use #[bitrange(short)] to map the pattern to the least significant bits"
+            } else {
+                ""
+            };
+            return Err(syn::Error::new_spanned(
+                &source,
+                format!(
+                    "{}: pattern {:?} has {} bits, but {} has {} bits{}",
+                    struct_name, original_pattern, bits, size_string, width, hint
+                ),
+            ));
+        }
+        // A width like `u24` is stored in the smallest integer that can hold it
+        if Pattern::get_odd_width(&size_string).is_some() {
+            size_string = Pattern::get_smallest_size(width).to_string();
+        }
+        let tokens = trimmed_pattern.chars().collect::<HashSet<_>>();
         Ok(Pattern {
-            struct_name: parsed.struct_name,
-            original_pattern: parsed.original_pattern,
-            trimmed_pattern: parsed.trimmed_pattern,
-            size: parsed.size,
+            struct_name,
+            size: size_string,
+            width,
+            trimmed_pattern,
+            original_pattern,
             tokens,
+            spans,
+            const_names: HashMap::new(),
+            bit_orders: HashMap::new(),
         })
     }
-    fn get_literal(iter: &mut ::proc_macro2::token_stream::IntoIter) -> Result<String, String> {
-        match iter.next() {
-            Some(TokenTree::Literal(lit)) => Ok(format!("{}", lit)),
-            Some(TokenTree::Ident(ident)) => Ok(format!("{}", ident)),
-            Some(TokenTree::Group(group)) => {
-                let mut iter = group.stream().into_iter();
-                Pattern::get_literal(&mut iter)
+
+    /// Returns the amount of bits in the given integer type, or `None` if the type is not supported
+    pub fn get_size_width(size: &str) -> Option<usize> {
+        match size.rsplit("::").next().map(str::trim) {
+            Some("u8") => Some(8),
+            Some("u16") => Some(16),
+            Some("u32") => Some(32),
+            Some("u64") => Some(64),
+            Some("u128") => Some(128),
+            _ => None,
+        }
+    }
+
+    /// Returns the amount of bytes of a `[u8; N]`, or `None` if the given type is not an array of bytes
+    pub fn get_array_length(size: &str) -> Option<usize> {
+        let size = size
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        match size.strip_prefix("[u8;")?.strip_suffix(']')?.parse() {
+            Ok(0) | Err(_) => None,
+            Ok(bytes) => Some(bytes),
+        }
+    }
+
+    /// Returns the amount of bits in the internal integer of a struct, which is an integer type or a `[u8; N]`
+    pub fn get_storage_width(size: &str) -> Option<usize> {
+        Pattern::get_size_width(size).or_else(|| Some(Pattern::get_array_length(size)? * 8))
+    }
+
+    /// Returns the amount of bits of a width that is not an integer type, like `u24` or `u48`,
+    /// or `None` if the given type is not such a width
+    pub fn get_odd_width(size: &str) -> Option<usize> {
+        if Pattern::get_size_width(size).is_some() {
+            return None;
+        }
+        match size.trim().strip_prefix('u')?.parse() {
+            Ok(bits) if bits > 0 && bits <= 128 => Some(bits),
+            _ => None,
+        }
+    }
+
+    /// Returns the amount of bits in the internal integer that hold the pattern.
+    /// This is less than the bits of the internal integer for a width like `u24`
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the amount of bytes that the internal integer is converted to, e.g. 3 for `u24`
+    pub fn get_bytes(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+
+    /// Returns true if the internal integer is a `[u8; N]`
+    pub fn is_array(&self) -> bool {
+        Pattern::get_array_length(&self.size).is_some()
+    }
+
+    /// Returns the type that the bits of a field are gathered in before they are converted to the type of the field.
+    /// This is the internal integer, or `u128` if the internal integer is a `[u8; N]`
+    pub fn get_bits_type(&self) -> &str {
+        if self.is_array() {
+            "u128"
+        } else {
+            &self.size
+        }
+    }
+
+    /// Returns the type in which the value of `token` is gathered and scattered.
+    /// This is `get_bits_type`, or `u128` if the value has more bits than the internal integer,
+    /// like `imm[17|16:12]` in a `u16`
+    pub fn get_token_bits_type(&self, token: char) -> &str {
+        match Pattern::get_size_width(&self.size) {
+            Some(width) if self.get_token_width(token) > width => "u128",
+            _ => self.get_bits_type(),
+        }
+    }
+
+    /// Returns the amount of bits in the given signed integer type, or `None` if the type is not a signed integer
+    pub fn get_signed_width(ty: &str) -> Option<usize> {
+        match ty.rsplit("::").next().map(str::trim) {
+            Some("i8") => Some(8),
+            Some("i16") => Some(16),
+            Some("i32") => Some(32),
+            Some("i64") => Some(64),
+            Some("i128") => Some(128),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the given type is a primitive type, like `u8` or `bool`
+    pub fn is_primitive(ty: &str) -> bool {
+        matches!(
+            ty.rsplit("::").next().map(str::trim),
+            Some(
+                "bool"
+                    | "char"
+                    | "f32"
+                    | "f64"
+                    | "str"
+                    | "i8"
+                    | "i16"
+                    | "i32"
+                    | "i64"
+                    | "i128"
+                    | "isize"
+                    | "u8"
+                    | "u16"
+                    | "u32"
+                    | "u64"
+                    | "u128"
+                    | "usize"
+            )
+        )
+    }
+
+    /// Returns the smallest integer type that can hold the given amount of bits
+    pub fn get_smallest_size(bits: usize) -> &'static str {
+        match bits {
+            0..=8 => "u8",
+            9..=16 => "u16",
+            17..=32 => "u32",
+            33..=64 => "u64",
+            _ => "u128",
+        }
+    }
+
+    /// Returns the smallest signed integer type that can hold the given amount of bits
+    pub fn get_smallest_signed_size(bits: usize) -> &'static str {
+        match bits {
+            0..=8 => "i8",
+            9..=16 => "i16",
+            17..=32 => "i32",
+            33..=64 => "i64",
+            _ => "i128",
+        }
+    }
+
+    fn trim(original_pattern: &str) -> String {
+        original_pattern
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+    }
+
+    /// Returns the characters of the fields in the pattern, in the order they first appear
+    pub fn get_field_tokens(&self) -> Vec<char> {
+        let mut tokens = Vec::new();
+        for c in self.trimmed_pattern.chars() {
+            if c != '0' && c != '1' && !tokens.contains(&c) {
+                tokens.push(c);
             }
-            x => Err(format!("Expected literal, got {:?}", x)),
         }
+        tokens
+    }
+
+    /// Returns the prefix of the associated constants of `token`, e.g. `A` for `A_MASK`.
+    /// Digits are prefixed with `_`, and an uppercase character gets an `_UPPER` suffix
+    /// when the pattern also contains the lowercase character.
+    pub fn get_token_const(&self, token: char) -> String {
+        if let Some(name) = self.const_names.get(&token) {
+            return name.clone();
+        }
+        let upper = token.to_uppercase().collect::<String>();
+        if token.is_numeric() {
+            format!("_{}", token)
+        } else if token.is_uppercase() && token.to_lowercase().any(|c| self.tokens.contains(&c)) {
+            format!("{}_UPPER", upper)
+        } else {
+            upper
+        }
+    }
+
+    /// Returns the span of the first occurrence of `token` in the pattern
+    pub fn get_token_span(&self, token: char) -> Span {
+        self.original_pattern
+            .chars()
+            .zip(self.spans.iter())
+            .find(|(c, _)| *c == token)
+            .map(|(_, span)| *span)
+            .unwrap_or_else(Span::call_site)
+    }
+
+    /// Concatenate the given tokens to a string, and return the span of every character.
+    /// String literals are unquoted, and invisible groups (created by `macro_rules!`) are flattened.
+    fn get_string(stream: &TokenStream2) -> (String, Vec<Span>) {
+        let mut str = String::new();
+        let mut spans = Vec::new();
+        for tree in stream.clone() {
+            let (part, part_spans) = match tree {
+                TokenTree::Group(ref group) if group.delimiter() == Delimiter::None => {
+                    Pattern::get_string(&group.stream())
+                }
+                TokenTree::Literal(ref lit) => {
+                    let span = lit.span();
+                    let part = match syn::parse2::<syn::LitStr>(tree.clone().into()) {
+                        Ok(lit) => lit.value().trim().to_string(),
+                        Err(_) => lit.to_string(),
+                    };
+                    let spans = vec![span; part.chars().count()];
+                    (part, spans)
+                }
+                tree => {
+                    let part = tree.to_string();
+                    let spans = vec![tree.span(); part.chars().count()];
+                    (part, spans)
+                }
+            };
+            str += &part;
+            spans.extend(part_spans);
+        }
+        (str, spans)
     }
-    fn parse(stream: TokenStream) -> Result<Parsed, String> {
-        let ast: syn::DeriveInput = syn::parse(stream).unwrap();
-        let mut parsed = Parsed::default();
-        parsed.struct_name = format!("{}", ast.ident);
-        for attr in ast.attrs {
-            let ident = attr
-                .path
-                .segments
+
+    /// Expand the repeat shorthand in the pattern, so `a{4}b{2}_1{2}` is `aaaabb_11`
+    fn expand(
+        struct_name: &str,
+        pattern: &str,
+        spans: &[Span],
+    ) -> syn::Result<(String, Vec<Span>)> {
+        let chars = pattern.chars().collect::<Vec<_>>();
+        let mut expanded = String::new();
+        let mut expanded_spans = Vec::new();
+        let mut index = 0;
+        while index < chars.len() {
+            if chars[index] != '{' {
+                expanded.push(chars[index]);
+                expanded_spans.push(spans[index]);
+                index += 1;
+                continue;
+            }
+            let end = chars[index..]
                 .iter()
-                .map(|s| format!("{}", s.ident))
-                .collect::<Vec<String>>()
-                .join("::");
-            if ident == "BitrangeMask" {
-                let mut iter = attr.tokens.into_iter();
-                match iter.next() {
-                    Some(TokenTree::Punct(ref p)) if p.as_char() == '=' => {}
-                    x => return Err(format!("Expected '#', got {:?}", x)),
+                .position(|&c| c == '}')
+                .map(|end| index + end);
+            let count = end.and_then(|end| {
+                chars[index + 1..end]
+                    .iter()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>()
+                    .parse::<usize>()
+                    .ok()
+            });
+            match (expanded.chars().last(), count, end) {
+                (Some(c), Some(count), Some(end)) if c != '_' && count > 0 => {
+                    for _ in 1..count {
+                        expanded.push(c);
+                        expanded_spans.push(spans[index]);
+                    }
+                    index = end + 1;
                 }
-                let original_pattern = Pattern::get_literal(&mut iter)?;
-                let original_pattern = original_pattern.trim_matches('"').trim().to_string();
-                let trimmed_pattern = original_pattern
-                    .chars()
-                    .filter(|c| c.is_alphanumeric())
-                    .collect::<String>();
-                parsed.original_pattern = original_pattern;
-                parsed.trimmed_pattern = trimmed_pattern;
-            } else if ident == "BitrangeSize" {
-                let mut iter = attr.tokens.into_iter();
-                match iter.next() {
-                    Some(TokenTree::Punct(ref p)) if p.as_char() == '=' => {}
-                    x => return Err(format!("Expected '#', got {:?}", x)),
+                _ => {
+                    return Err(syn::Error::new(
+                        spans[index],
+                        format!(
+                            "{}: invalid repeat in pattern {:?}, expected a character and an amount of bits like `a{{4}}`",
+                            struct_name, pattern
+                        ),
+                    ))
                 }
-                let size = Pattern::get_literal(&mut iter)?;
-                parsed.size = size.trim_matches('"').to_string();
             }
         }
-        if parsed.trimmed_pattern.is_empty() || parsed.original_pattern.is_empty() {
-            Err("Missing attribute #[BitrangeMask = \"...\"]".to_string())
-        } else if parsed.size.is_empty() {
-            Err("Missing attribute #[BitrangeSize = \"...\"]".to_string())
-        } else {
-            Ok(parsed)
-        }
+        Ok((expanded, expanded_spans))
     }
 
     pub fn get_token_mask(&self, token: char) -> String {
-        let mut str = String::with_capacity(self.original_pattern.len() + 2);
-        str += "0b";
-        for c in self.original_pattern.chars() {
-            str += if c == '_' {
-                "_"
-            } else if c == token {
-                "1"
-            } else {
-                "0"
-            };
+        self.get_literal(false, |c| c == token)
+    }
+
+    /// Returns the amount of bits that are mapped to `token` in the pattern
+    pub fn get_token_bits(&self, token: char) -> usize {
+        self.trimmed_pattern.chars().filter(|c| *c == token).count()
+    }
+
+    /// Returns the amount of bits of the value of `token`.
+    /// This is the amount of bits that are mapped to `token`, plus the bits that are always 0 in its order
+    pub fn get_token_width(&self, token: char) -> usize {
+        match self
+            .bit_orders
+            .get(&token)
+            .and_then(|bits| bits.iter().max())
+        {
+            Some(high) => high + 1,
+            None => self.get_token_bits(token),
         }
-        str
+    }
+
+    /// Returns the amount of least significant bits of the value of `token` that are always 0,
+    /// like bit 0 of the immediate in `i: imm[12|10:5|4:1|11]`
+    pub fn get_token_zeros(&self, token: char) -> usize {
+        self.bit_orders
+            .get(&token)
+            .and_then(|bits| bits.iter().min().copied())
+            .unwrap_or_default()
     }
 
     pub fn get_token_offset(&self, token: char) -> usize {
@@ -111,33 +445,91 @@ impl Pattern {
             .count()
     }
 
-    pub fn get_default_mask(&self) -> String {
-        let mut str = String::with_capacity(self.original_pattern.len() + 2);
-        str += "0b";
-        for c in self.original_pattern.chars() {
-            str += if c == '_' {
-                "_"
-            } else if c == '0' || c == '1' {
-                "1"
-            } else {
-                "0"
+    /// Returns the ranges of consecutive bits that are mapped to `token`, starting at the least significant bit.
+    ///
+    /// e.g. the token `a` in `aabb_aabb` has two runs:
+    /// - bits 2..4, which are bits 0..2 of the field
+    /// - bits 6..8, which are bits 2..4 of the field
+    ///
+    /// With an order in `bit_orders`, a run also ends where the next bit is not the next bit of the value
+    pub fn get_token_runs(&self, token: char) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        // The order is from the first to the last bit in the pattern, the runs start at the least significant bit
+        let mut order = self.bit_orders.get(&token).into_iter().flatten().rev();
+        let mut next_offset = 0;
+        for (offset, c) in self.trimmed_pattern.chars().rev().enumerate() {
+            if c != token {
+                continue;
+            }
+            let field_offset = order.next().copied().unwrap_or(next_offset);
+            next_offset += 1;
+            match runs.last_mut() {
+                Some(run)
+                    if run.offset + run.width == offset
+                        && run.field_offset + run.width == field_offset =>
+                {
+                    run.width += 1
+                }
+                _ => runs.push(Run {
+                    offset,
+                    width: 1,
+                    field_offset,
+                }),
             }
         }
-        str
+        runs
+    }
+
+    /// Returns a literal with the bits that are always 0 or 1.
+    /// The bits above a width like `u24` are always 0, so they are part of this mask
+    pub fn get_default_mask(&self) -> String {
+        self.get_literal(true, |c| c == '0' || c == '1')
     }
 
     pub fn get_default_value(&self) -> String {
-        let mut str = String::with_capacity(self.original_pattern.len() + 2);
-        str += "0b";
-        for c in self.original_pattern.chars() {
-            str += if c == '_' {
-                "_"
-            } else if c == '1' {
-                "1"
-            } else {
-                "0"
+        self.get_literal(false, |c| c == '1')
+    }
+
+    /// Returns a literal of the internal integer, where the bits for which `is_set` returns true are 1.
+    /// Integers keep the underscores of the pattern, e.g. `0b0000_1100`,
+    /// and a `[u8; N]` is written as an array of bytes, e.g. `[0b00000000, 0b00001100]`.
+    /// The bits above a width like `u24` are 1 if `padding` is true
+    fn get_literal(&self, padding: bool, is_set: impl Fn(char) -> bool) -> String {
+        if !self.is_array() {
+            let mut str = String::with_capacity(self.original_pattern.len() + 2);
+            str += "0b";
+            let storage_width = Pattern::get_size_width(&self.size).unwrap_or_default();
+            if storage_width > self.width {
+                str += &(if padding { "1" } else { "0" }).repeat(storage_width - self.width);
+                str += "_";
+                str += &"0".repeat(self.width - self.trimmed_pattern.chars().count());
+            }
+            for c in self.original_pattern.chars() {
+                str += if c == '_' {
+                    "_"
+                } else if is_set(c) {
+                    "1"
+                } else {
+                    "0"
+                };
             }
+            return str;
         }
-        str
+        // The pattern is aligned to the least significant bits, the bits above it are 0
+        let padding = self.get_width() - self.trimmed_pattern.chars().count();
+        let bits = std::iter::repeat_n(false, padding)
+            .chain(self.trimmed_pattern.chars().map(is_set))
+            .collect::<Vec<_>>();
+        let bytes = bits
+            .chunks(8)
+            .map(|byte| {
+                let byte = byte
+                    .iter()
+                    .map(|bit| if *bit { '1' } else { '0' })
+                    .collect::<String>();
+                format!("0b{}", byte)
+            })
+            .collect::<Vec<_>>();
+        format!("[{}]", bytes.join(", "))
     }
 }
@@ -3,12 +3,27 @@
 #[cfg(test)]
 #[macro_use]
 extern crate bitrange;
-#[cfg(test)]
-#[macro_use]
-extern crate bitrange_plugin;
 
+pub mod test_arrays;
+pub mod test_builder;
+pub mod test_bytes;
+pub mod test_const;
+pub mod test_conversions;
+pub mod test_derive;
+pub mod test_diagram;
+pub mod test_diagram_macro;
+pub mod test_enums;
+pub mod test_formats;
 pub mod test_ip;
+pub mod test_nested;
+pub mod test_noncontiguous;
+pub mod test_numbering;
 pub mod test_panics;
+pub mod test_setters;
+pub mod test_signed;
+pub mod test_sizes;
+pub mod test_syntax;
+pub mod test_types;
 
 #[test]
 fn test_default() {
@@ -30,7 +45,8 @@ fn test_default_2() {
     bitrange! {
         Test: u8, "u8",
         "aaa1_0bbb",
-        a: first
+        a: first,
+        b: _
     }
 
     let test = Test::default();
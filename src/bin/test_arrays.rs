@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod test_ipv4 {
+    use bitrange::BytesError;
+
+    // The full header of RFC 791, section 3.1, without options
+    bitrange! {
+        Ipv4Header: [u8; 20],
+        aaaa_bbbb_cccccccc_dddddddddddddddd_eeeeeeeeeeeeeeee_0fgiiiiiiiiiiiii_jjjjjjjj_kkkkkkkk_llllllllllllllll_mmmmmmmmmmmmmmmmmmmmmmmmmmmmmmmm_nnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnn,
+        a: version,
+        b: ihl,
+        c: type_of_service,
+        d: total_length set_total_length,
+        e: identification,
+        f: dont_fragment set_dont_fragment,
+        g: more_fragments set_more_fragments,
+        i: fragment_offset set_fragment_offset,
+        j: time_to_live set_time_to_live,
+        k: protocol,
+        l: header_checksum,
+        m: source,
+        n: destination
+    }
+
+    const BYTES: [u8; 20] = [
+        0x45, 0x00, 0x00, 0x40, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x11, 0xb1, 0xe6, 0xc0, 0xa8, 0x02,
+        0x02, 0xc0, 0xa8, 0x02, 0x04,
+    ];
+
+    #[test]
+    fn test_getters() {
+        let header = Ipv4Header::try_from(BYTES).unwrap();
+        assert_eq!(header.version(), 4);
+        assert_eq!(header.ihl(), 5);
+        assert_eq!(header.type_of_service(), 0);
+        assert_eq!(header.total_length(), 64);
+        assert_eq!(header.identification(), 0x1c46);
+        assert!(header.dont_fragment());
+        assert!(!header.more_fragments());
+        assert_eq!(header.fragment_offset(), 0);
+        assert_eq!(header.time_to_live(), 64);
+        assert_eq!(header.protocol(), 17);
+        assert_eq!(header.header_checksum(), 0xb1e6);
+        assert_eq!(header.source(), 0xc0a8_0202);
+        assert_eq!(header.destination(), 0xc0a8_0204);
+
+        // The first byte holds the first bits of the pattern, in every byte order of the slice
+        assert_eq!(header.bits(), BYTES);
+        assert_eq!(header.to_be_bytes(), BYTES);
+        assert_eq!(Ipv4Header::try_from(&BYTES[..]), Ok(header));
+        let mut reversed = BYTES;
+        reversed.reverse();
+        assert_eq!(header.to_le_bytes(), reversed);
+        assert_eq!(Ipv4Header::from_le_bytes(reversed), Ok(header));
+    }
+
+    #[test]
+    fn test_setters() {
+        let mut header = Ipv4Header::try_from(BYTES).unwrap();
+        // The fragment offset starts in the middle of byte 6
+        header
+            .set_fragment_offset(0x1abc)
+            .set_more_fragments(true)
+            .set_dont_fragment(false)
+            .set_total_length(0x1234)
+            .set_time_to_live(1);
+        assert_eq!(header.fragment_offset(), 0x1abc);
+        assert_eq!(header.bits()[2..4], [0x12, 0x34]);
+        assert_eq!(header.bits()[6..9], [0x3a, 0xbc, 0x01]);
+        assert_eq!(header.source(), 0xc0a8_0202);
+
+        header.toggle_more_fragments();
+        assert_eq!(header.bits()[6], 0x1a);
+
+        let built = Ipv4Header::builder()
+            .version(4)
+            .ihl(5)
+            .total_length(64)
+            .identification(0x1c46)
+            .dont_fragment(true)
+            .time_to_live(64)
+            .protocol(17)
+            .header_checksum(0xb1e6)
+            .source(0xc0a8_0202)
+            .destination(0xc0a8_0204)
+            .build();
+        assert_eq!(built.bits(), BYTES);
+    }
+
+    #[test]
+    fn test_invalid_bits() {
+        let mut bytes = BYTES;
+        bytes[6] |= 0x80;
+        let e = Ipv4Header::try_from(bytes).unwrap_err();
+        assert_eq!(e.provided[6], 0x80);
+        // Bit 0 is the least significant bit of the last byte
+        assert_eq!(e.positions().collect::<Vec<_>>(), vec![111]);
+        assert_eq!(e.to_string(), "Ipv4Header: bit 111 must be 0");
+        assert_eq!(
+            Ipv4Header::try_from(&bytes[..]),
+            Err(BytesError::InvalidBits(e))
+        );
+        assert_eq!(Ipv4Header::from_bits_masked(bytes).bits(), BYTES);
+    }
+
+    #[test]
+    fn test_formats() {
+        let header = Ipv4Header::try_from(BYTES).unwrap();
+        assert_eq!(
+            format!("{:x}", header),
+            "450000401c4640004011b1e6c0a80202c0a80204"
+        );
+        assert_eq!(
+            format!("{:#X}", header),
+            "0x450000401C4640004011B1E6C0A80202C0A80204"
+        );
+        assert_eq!(
+            format!("{:b}", Ipv4Header::from_bits_unchecked([0; 20])),
+            "0"
+        );
+        assert!(format!("{:#b}", header).starts_with("0b0100_0101_00000000_"));
+
+        let diagram = header.to_string();
+        assert!(
+            diagram.contains("|version|  ihl  |type_of_service|         total_length          |")
+        );
+        assert!(
+            diagram.contains("|                            source                             |")
+        );
+        assert!(
+            diagram.contains("|                          3232236034                           |")
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_wide {
+    bitrange! {
+        Test: [u8; 16],
+        aaaa_bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb,
+        a: kind,
+        b: payload set_payload
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::default();
+        test.set_payload(0x0012_3456_789a_bcde_f012_3456_789a_bcde);
+        assert_eq!(test.kind(), 0);
+        assert_eq!(test.payload(), 0x0012_3456_789a_bcde_f012_3456_789a_bcde);
+        assert_eq!(test.bits()[0], 0x00);
+        assert_eq!(test.bits()[15], 0xde);
+
+        let test = Test::new(0xf, u128::MAX >> 4);
+        assert_eq!(test.bits(), [0xff; 16]);
+    }
+}
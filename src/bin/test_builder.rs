@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod test_new {
+    bitrange! {
+        Test: u16,
+        aaa1_0bbb_cccc_cccd,
+        a: first,
+        b: second,
+        c: third signed,
+        d: flag
+    }
+
+    const TEST: Test = Test::new(0b101, 0b011, -1, true);
+
+    #[test]
+    #[allow(clippy::unusual_byte_groupings)]
+    fn test() {
+        assert_eq!(TEST.bits, 0b1011_0011_1111_1111);
+        assert_eq!(TEST.first(), 0b101);
+        assert_eq!(TEST.third(), -1);
+
+        let test = Test::new(0, 0, 0, false);
+        assert_eq!(test.bits, 0b0001_0000_0000_0000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Test: value does not fit in the 3 bits of field `second`")]
+    fn test_overflow() {
+        let second = 8;
+        Test::new(0, second, 0, false);
+    }
+}
+
+#[cfg(test)]
+mod test_ip_header {
+    #[derive(BitrangeEnum, Debug, PartialEq, Eq)]
+    pub enum Protocol {
+        Icmp = 1,
+        Tcp = 6,
+        Udp = 17,
+    }
+
+    bitrange! {
+        IpHeader: u32,
+        aaaa_bbbb_cccccc_dd_pppppppp_eeeeeeee,
+        a: version,
+        b: ihl,
+        c: dscp,
+        d: ecn,
+        p: protocol as Protocol,
+        e: ttl
+    }
+
+    #[test]
+    fn test() {
+        let header = IpHeader::builder()
+            .version(4)
+            .ihl(5)
+            .protocol(Protocol::Udp)
+            .build();
+        assert_eq!(header.bits, 0x4500_1100);
+        assert_eq!(header.protocol(), Ok(Protocol::Udp));
+
+        let header = IpHeader::strict_builder()
+            .version(4)
+            .ihl(5)
+            .dscp(0)
+            .ecn(1)
+            .protocol(Protocol::Tcp)
+            .ttl(64)
+            .build();
+        assert_eq!(header.bits, 0x4501_0640);
+    }
+
+    #[test]
+    #[should_panic(expected = "IpHeader: value does not fit in the 4 bits of field `ihl`")]
+    fn test_overflow() {
+        IpHeader::builder().ihl(16);
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod test_byte_order {
+    use bitrange::BytesError;
+
+    bitrange! {
+        IpHeader: u32,
+        aaaa_bbbb_cccccccc_dddddddddddddddd,
+        a: version,
+        b: ihl,
+        c: type_of_service,
+        d: total_length
+    }
+
+    #[test]
+    fn test() {
+        let header = IpHeader::from_be_bytes([0x45, 0x00, 0x05, 0xdc]).unwrap();
+        assert_eq!((header.version(), header.ihl()), (4, 5));
+        assert_eq!(header.total_length(), 1500);
+        assert_eq!(header.to_be_bytes(), [0x45, 0x00, 0x05, 0xdc]);
+        assert_eq!(header.to_le_bytes(), [0xdc, 0x05, 0x00, 0x45]);
+        assert_eq!(
+            IpHeader::from_le_bytes([0xdc, 0x05, 0x00, 0x45]),
+            Ok(header)
+        );
+        assert_eq!(IpHeader::from_ne_bytes(header.to_ne_bytes()), Ok(header));
+    }
+
+    #[test]
+    fn test_slice() {
+        // Big-endian by default
+        let header = IpHeader::try_from(&[0x45, 0x00, 0x05, 0xdc][..]).unwrap();
+        assert_eq!(header.total_length(), 1500);
+        assert_eq!(<[u8; 4]>::from(header), [0x45, 0x00, 0x05, 0xdc]);
+
+        let error = IpHeader::try_from(&[0x45, 0x00, 0x05][..]).unwrap_err();
+        assert_eq!(
+            error,
+            BytesError::Length {
+                name: "IpHeader",
+                expected: 4,
+                provided: 3
+            }
+        );
+        assert_eq!(error.to_string(), "IpHeader: expected 4 bytes, got 3");
+    }
+}
+
+#[cfg(test)]
+mod test_declared_order {
+    use bitrange::BytesError;
+
+    bitrange! {
+        #[bitrange(byte_order = "little")]
+        Register: u16,
+        1aaa_aaaa_bbbb_bbbb,
+        a: high,
+        b: low
+    }
+
+    #[test]
+    fn test() {
+        let register = Register::try_from(&[0x34, 0x92][..]).unwrap();
+        assert_eq!((register.high(), register.low()), (0x12, 0x34));
+        assert_eq!(<[u8; 2]>::from(register), [0x34, 0x92]);
+
+        let error = Register::try_from(&[0x34, 0x12][..]).unwrap_err();
+        assert!(matches!(error, BytesError::InvalidBits(_)));
+        assert_eq!(error.to_string(), "Register: bit 15 must be 1");
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod test_consts {
+    bitrange! {
+        Test: u16,
+        aaa1_0bbb_bccc_cAcc,
+        a: first,
+        b: second,
+        c: third,
+        A: flag
+    }
+
+    #[test]
+    #[allow(clippy::unusual_byte_groupings)]
+    fn test() {
+        assert_eq!(Test::A_MASK, 0b1110_0000_0000_0000);
+        assert_eq!(Test::A_OFFSET, 13);
+        assert_eq!(Test::A_WIDTH, 3);
+
+        assert_eq!(Test::B_MASK, 0b0000_0111_1000_0000);
+        assert_eq!(Test::B_OFFSET, 7);
+        assert_eq!(Test::B_WIDTH, 4);
+
+        assert_eq!(Test::C_MASK, 0b0000_0000_0111_1011);
+        assert_eq!(Test::C_OFFSET, 0);
+        assert_eq!(Test::C_WIDTH, 6);
+
+        assert_eq!(Test::A_UPPER_MASK, 0b0000_0000_0000_0100);
+        assert_eq!(Test::A_UPPER_OFFSET, 2);
+        assert_eq!(Test::A_UPPER_WIDTH, 1);
+    }
+}
+
+#[cfg(test)]
+mod test_const_fn {
+    bitrange! {
+        IpHeader: u32,
+        aaaa_bbbb_cccccccc_dddddddddddddddd,
+        a: version set_version,
+        b: ihl set_ihl,
+        c: type_of_service,
+        d: total_length set_total_length
+    }
+
+    const HEADER: IpHeader = IpHeader::builder().version(4).ihl(5).build();
+    const VERSION: u8 = HEADER.version();
+
+    #[allow(deprecated)]
+    const FROM: IpHeader = match IpHeader::from(0x4500_0014) {
+        Ok(header) => header,
+        Err(_) => panic!("invalid header"),
+    };
+    const RAW: IpHeader = IpHeader::from_bits_unchecked(0x4500_0014);
+    const BITS: u32 = RAW.bits();
+
+    const fn with_length(length: u16) -> IpHeader {
+        let mut header = HEADER;
+        header.set_total_length(length).set_ihl_saturating(100);
+        header
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(HEADER.bits, 0x4500_0000);
+        assert_eq!(VERSION, 4);
+        assert_eq!(FROM.total_length(), 20);
+        assert_eq!(RAW, FROM);
+        assert_eq!(BITS, 0x4500_0014);
+
+        const LONG: IpHeader = with_length(1500);
+        assert_eq!(LONG.bits, 0x4f00_05dc);
+    }
+}
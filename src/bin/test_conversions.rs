@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test_std_conversions {
+    bitrange! {
+        Test: u8,
+        aaa1_0bbb,
+        a: first,
+        b: second
+    }
+
+    fn parse<T: TryFrom<u8>>(bits: u8) -> Option<T> {
+        T::try_from(bits).ok()
+    }
+
+    fn first(bits: u8) -> Result<u8, bitrange::Error<u8>> {
+        Ok(Test::try_from(bits)?.first())
+    }
+
+    #[test]
+    fn test() {
+        let test: Test = parse(0b1011_0011).unwrap();
+        assert_eq!((test.first(), test.second()), (0b101, 0b011));
+        assert_eq!(u8::from(test), 0b1011_0011);
+        let bits: u8 = test.into();
+        assert_eq!(bits, 0b1011_0011);
+
+        assert_eq!(first(0b1011_0011).ok(), Some(0b101));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse::<Test>(0b1010_0011).is_none());
+        assert!(first(0b1010_0011).is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_raw_bits {
+    bitrange! {
+        Test: u8,
+        aaa1_0bbb,
+        a: first,
+        b: second
+    }
+
+    #[test]
+    fn test() {
+        let test = Test::new(0b101, 0b011);
+        assert_eq!(test.bits(), 0b1011_0011);
+        assert_eq!(test.into_bits(), 0b1011_0011);
+
+        // The bits that are always 0 or 1 are not checked
+        let test = Test::from_bits_unchecked(0b1010_1011);
+        assert_eq!(test.bits(), 0b1010_1011);
+        assert_eq!((test.first(), test.second()), (0b101, 0b011));
+    }
+}
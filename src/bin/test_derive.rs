@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod test_defaults {
+    use std::collections::HashSet;
+
+    #[derive(BitrangeEnum)]
+    pub enum Protocol {
+        Icmp = 1,
+        Tcp = 6,
+        Udp = 17,
+    }
+
+    bitrange! {
+        Test: u16,
+        aaa1_bccc_pppp_pppp,
+        a: first,
+        b: flag,
+        c: _,
+        p: protocol as Protocol
+    }
+
+    #[test]
+    fn test() {
+        let test = Test::try_from(0b1011_1000_0001_0001).unwrap();
+        let copy = test;
+        assert_eq!(test, copy);
+        assert_ne!(test, Test::default());
+
+        let mut set = HashSet::new();
+        set.insert(test);
+        assert!(set.contains(&copy));
+
+        assert_eq!(
+            format!("{:?}", test),
+            "Test { first: 5, flag: true, protocol: Udp }"
+        );
+        let test = Test::try_from(0b0001_0000_0000_0010).unwrap();
+        assert_eq!(
+            format!("{:?}", test),
+            "Test { first: 0, flag: false, protocol: InvalidValue(2) }"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_derive_option {
+    use std::fmt;
+
+    bitrange! {
+        #[bitrange(derive(Clone, Copy, PartialEq))]
+        Test: u8,
+        aaaa_bbbb,
+        a: first,
+        b: second
+    }
+
+    // `Debug` is not implemented by bitrange, so it can be implemented by hand
+    impl fmt::Debug for Test {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Test({:#x})", self.bits)
+        }
+    }
+
+    #[test]
+    fn test() {
+        let test = Test::new(1, 2);
+        assert_eq!(test, test.clone());
+        assert_eq!(format!("{:?}", test), "Test(0x12)");
+    }
+}
+
+#[cfg(test)]
+mod test_derive_attribute {
+    // The traits that bitrange implements are removed from `#[derive(...)]`, the others are kept
+    bitrange! {
+        #[derive(Debug, Clone, PartialOrd)]
+        Test: u8,
+        aaaa_bbbb,
+        a: first,
+        b: second
+    }
+
+    #[test]
+    fn test() {
+        let test = Test::new(1, 2);
+        assert_eq!(
+            format!("{:?}", test.clone()),
+            "Test { first: 1, second: 2 }"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod test_display {
+    use bitrange::{InvalidRowBits, RowBits};
+
+    // RFC 791 numbers the bits from the most significant bit
+    bitrange! {
+        #[bitrange(bit_numbering = "msb0")]
+        IpHeader: u32,
+        aaaa_bbbb_cccccccc_dddddddddddddddd,
+        a: version,
+        b: ihl,
+        c: type_of_service,
+        d: total_length
+    }
+
+    #[test]
+    fn test() {
+        let header = IpHeader::new(4, 5, 0, 1500);
+        assert_eq!(
+            header.to_string(),
+            [
+                " 0                   1                   2                   3",
+                " 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+                "|version|  ihl  |type_of_service|         total_length          |",
+                "|   4   |   5   |       0       |             1500              |",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_rows() {
+        let header = IpHeader::new(4, 5, 0, 1500);
+        assert_eq!(
+            header.to_diagram(RowBits::B16).to_string(),
+            [
+                " 0                   1",
+                " 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+                "|version|  ihl  |type_of_service|",
+                "|   4   |   5   |       0       |",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+                "|         total_length          |",
+                "|             1500              |",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+            ]
+            .join("\n")
+        );
+
+        // `total_length` is split over the last two rows
+        assert_eq!(
+            header.to_diagram(RowBits::B8).to_string(),
+            [
+                " 0 1 2 3 4 5 6 7",
+                "+-+-+-+-+-+-+-+-+",
+                "|version|  ihl  |",
+                "|   4   |   5   |",
+                "+-+-+-+-+-+-+-+-+",
+                "|type_of_service|",
+                "|       0       |",
+                "+-+-+-+-+-+-+-+-+",
+                "| total_length  |",
+                "|     1500      |",
+                "+-+-+-+-+-+-+-+-+",
+                "| total_length  |",
+                "|     1500      |",
+                "+-+-+-+-+-+-+-+-+",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_row_bits() {
+        assert_eq!(RowBits::try_from(8), Ok(RowBits::B8));
+        assert_eq!(RowBits::try_from(16), Ok(RowBits::B16));
+        assert_eq!(RowBits::try_from(32), Ok(RowBits::B32));
+        assert_eq!(RowBits::B16.bits(), 16);
+
+        assert_eq!(RowBits::try_from(0), Err(InvalidRowBits::new(0)));
+        assert_eq!(RowBits::try_from(12), Err(InvalidRowBits::new(12)));
+        assert_eq!(RowBits::try_from(64), Err(InvalidRowBits::new(64)));
+    }
+}
+
+#[cfg(test)]
+mod test_cells {
+    use bitrange::RowBits;
+
+    #[derive(BitrangeEnum, Debug, PartialEq, Eq)]
+    pub enum Protocol {
+        Icmp = 1,
+        Tcp = 6,
+        Udp = 17,
+    }
+
+    bitrange! {
+        Test: u32,
+        aaa1_0bcc_rrrr_rrrr_pppppppp_aaaaaaaa,
+        a: first,
+        b: flag,
+        c: second signed,
+        p: protocol as Protocol,
+        r: _
+    }
+
+    #[test]
+    #[allow(clippy::unusual_byte_groupings)]
+    fn test() {
+        let test = Test::try_from(0b101_1_0_1_11_0000_0011_00010001_00000010).unwrap();
+        // Both cells of `first` show the value of the whole field.
+        // Bit 0 is the least significant bit, so the header counts down from bit 31
+        assert_eq!(
+            test.to_diagram(RowBits::B16).to_string(),
+            [
+                "   3                   2",
+                " 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+                "|first|   |f|sec|               |",
+                "|1282 |10 |1|-1 |       3       |",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+                "|   protocol    |     first     |",
+                "|      Udp      |     1282      |",
+                "+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+",
+            ]
+            .join("\n")
+        );
+    }
+}
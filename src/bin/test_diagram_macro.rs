@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod test_ipv4_diagram {
+    // RFC 791, section 3.1, without the options
+    bitrange_diagram! {
+        Ipv4Header: [u8; 20],
+        r"
+            0                   1                   2                   3
+            0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |Version|  IHL  |Type of Service|          Total Length         |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |         Identification        |Flags|      Fragment Offset    |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |  Time to Live |    Protocol   |         Header Checksum       |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |                       Source Address                          |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |                    Destination Address                        |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+        "
+    }
+
+    const BYTES: [u8; 20] = [
+        0x45, 0x00, 0x00, 0x40, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x11, 0xb1, 0xe6, 0xc0, 0xa8, 0x02,
+        0x02, 0xc0, 0xa8, 0x02, 0x04,
+    ];
+
+    #[test]
+    fn test() {
+        let mut header = Ipv4Header::try_from(BYTES).unwrap();
+        assert_eq!(header.version(), 4);
+        assert_eq!(header.ihl(), 5);
+        assert_eq!(header.type_of_service(), 0);
+        assert_eq!(header.total_length(), 64);
+        assert_eq!(header.identification(), 0x1c46);
+        assert_eq!(header.flags(), 0b010);
+        assert_eq!(header.fragment_offset(), 0);
+        assert_eq!(header.time_to_live(), 64);
+        assert_eq!(header.protocol(), 17);
+        assert_eq!(header.header_checksum(), 0xb1e6);
+        assert_eq!(header.source_address(), 0xc0a8_0202);
+        assert_eq!(header.destination_address(), 0xc0a8_0204);
+
+        header.set_time_to_live(1).set_fragment_offset(0x1abc);
+        assert_eq!(header.bits()[6..9], [0x5a, 0xbc, 0x01]);
+
+        // The widths of the fields are the same as the widths of the cells
+        assert_eq!(Ipv4Header::FLAGS_WIDTH, 3);
+        assert_eq!(
+            (Ipv4Header::FLAGS_FIRST_BIT, Ipv4Header::FLAGS_LAST_BIT),
+            (48, 50)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_ipv6_diagram {
+    // RFC 8200, section 3, where the addresses span 4 rows
+    bitrange_diagram! {
+        Ipv6Header: [u8; 40],
+        r"
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |Version| Traffic Class |           Flow Label                  |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |         Payload Length        |  Next Header  |   Hop Limit   |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |                                                               |
+           +                                                               +
+           |                                                               |
+           +                         Source Address                        +
+           |                                                               |
+           +                                                               +
+           |                                                               |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |                                                               |
+           +                                                               +
+           |                                                               |
+           +                      Destination Address                      +
+           |                                                               |
+           +                                                               +
+           |                                                               |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+        "
+    }
+
+    #[test]
+    fn test() {
+        let mut bytes = [0; 40];
+        bytes[..8].copy_from_slice(&[0x60, 0x0a, 0xbc, 0xde, 0x00, 0x20, 0x11, 0x40]);
+        bytes[8] = 0x20;
+        bytes[9] = 0x01;
+        bytes[39] = 0x01;
+        bytes[24] = 0xfe;
+        bytes[25] = 0x80;
+        let mut header = Ipv6Header::try_from(bytes).unwrap();
+        assert_eq!(header.version(), 6);
+        assert_eq!(header.traffic_class(), 0);
+        assert_eq!(header.flow_label(), 0xabcde);
+        assert_eq!(header.payload_length(), 32);
+        assert_eq!(header.next_header(), 17);
+        assert_eq!(header.hop_limit(), 64);
+        assert_eq!(header.source_address(), 0x2001 << 112);
+        assert_eq!(header.destination_address(), 0xfe80 << 112 | 1);
+
+        header.set_destination_address(u128::MAX);
+        assert_eq!(header.bits()[24..], [0xff; 16]);
+        assert_eq!(Ipv6Header::DESTINATION_ADDRESS_WIDTH, 128);
+    }
+}
+
+#[cfg(test)]
+mod test_tcp_diagram {
+    // A row of RFC 793, section 3.1, with labels over multiple lines
+    bitrange_diagram! {
+        #[bitrange(bit_numbering = "lsb0")]
+        TcpControl: u32,
+        r"
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+           |  Data |           |U|A|P|R|S|F|                               |
+           | Offset| Reserved  |R|C|S|S|Y|I|            Window             |
+           |       |           |G|K|H|T|N|N|                               |
+           +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+        "
+    }
+
+    #[test]
+    fn test() {
+        let mut control = TcpControl::try_from(0x5012_faf0).unwrap();
+        assert_eq!(control.data_offset(), 5);
+        assert!(control.ack());
+        assert!(control.syn());
+        assert!(!control.fin());
+        assert_eq!(control.window(), 0xfaf0);
+
+        control.set_fin(true).toggle_syn();
+        assert_eq!(control.bits(), 0x5011_faf0);
+
+        // The reserved bits are kept, but have no getter
+        let control = TcpControl::try_from(0x5f00_0000).unwrap();
+        assert_eq!(control.data_offset(), 5);
+        assert_eq!(
+            (TcpControl::WINDOW_FIRST_BIT, TcpControl::WINDOW_LAST_BIT),
+            (15, 0)
+        );
+    }
+}
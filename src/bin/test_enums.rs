@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod test_protocol {
+    use bitrange::InvalidValue;
+
+    #[derive(BitrangeEnum, Debug, PartialEq, Eq)]
+    pub enum Protocol {
+        Icmp = 1,
+        Tcp = 6,
+        Udp = 17,
+    }
+
+    bitrange! {
+        Test: u16,
+        aaaa_aaaa_bbbb_bbbb,
+        a: ttl set_ttl,
+        b: protocol set_protocol as Protocol
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::try_from(0x4006).unwrap();
+        assert_eq!(test.protocol(), Ok(Protocol::Tcp));
+
+        test.set_protocol(Protocol::Udp);
+        assert_eq!(test.bits, 0x4011);
+        assert_eq!(test.protocol(), Ok(Protocol::Udp));
+        assert_eq!(test.ttl(), 0x40);
+
+        let test = Test::try_from(0x4002).unwrap();
+        assert_eq!(test.protocol(), Err(InvalidValue { value: 2 }));
+    }
+}
+
+#[cfg(test)]
+mod test_exhaustive {
+    #[derive(BitrangeEnum, Debug, PartialEq, Eq)]
+    pub enum Precedence {
+        Routine,
+        Priority,
+        Immediate,
+        Flash,
+    }
+
+    bitrange! {
+        Test: u8,
+        aabb_bccc,
+        a: precedence set_precedence as Precedence,
+        b: _,
+        c: wide set_wide as Precedence
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::default();
+        let precedence: Precedence = test.precedence();
+        assert_eq!(precedence, Precedence::Routine);
+
+        test.set_precedence(Precedence::Flash);
+        assert_eq!(test.bits, 0b1100_0000);
+        assert_eq!(test.precedence(), Precedence::Flash);
+
+        test.set_precedence(Precedence::Priority);
+        assert_eq!(test.precedence(), Precedence::Priority);
+
+        // A wider field can hold values that are not a variant
+        test.set_wide(Precedence::Immediate);
+        assert_eq!(test.bits, 0b0100_0010);
+        assert_eq!(test.wide(), Ok(Precedence::Immediate));
+        test.bits = 0b0100_0111;
+        assert!(test.wide().is_err());
+    }
+
+    #[test]
+    fn test_noncontiguous() {
+        bitrange! {
+            Split: u8,
+            abbb_bbba,
+            a: precedence set_precedence as Precedence,
+            b: _
+        }
+
+        let mut test = Split::default();
+        test.set_precedence(Precedence::Immediate);
+        assert_eq!(test.bits, 0b1000_0000);
+        assert_eq!(test.precedence(), Precedence::Immediate);
+    }
+}
@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod test_radix {
+    bitrange! {
+        IpHeader: u32,
+        aaaa_bbbb_cccccccc_dddddddddddddddd,
+        a: version,
+        b: ihl,
+        c: type_of_service,
+        d: total_length
+    }
+
+    #[test]
+    fn test() {
+        let header = IpHeader::new(4, 5, 0, 1500);
+        assert_eq!(format!("{:b}", header), "1000101000000000000010111011100");
+        assert_eq!(format!("{:x}", header), "450005dc");
+        assert_eq!(format!("{:#X}", header), "0x450005DC");
+        assert_eq!(format!("{:o}", header), "10500002734");
+        assert_eq!(format!("{:012x}", header), "0000450005dc");
+    }
+
+    #[test]
+    fn test_grouped() {
+        let header = IpHeader::new(4, 5, 0, 1500);
+        assert_eq!(
+            format!("{:#b}", header),
+            "0b0100_0101_00000000_0000010111011100\n  \
+             aaaa_bbbb_cccccccc_dddddddddddddddd  \
+             a: version, b: ihl, c: type_of_service, d: total_length"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_short {
+    bitrange! {
+        #[bitrange(short)]
+        Test: u16,
+        aaa1_0bbb,
+        a: first,
+        b: _
+    }
+
+    #[test]
+    fn test_grouped() {
+        assert_eq!(
+            format!("{:#b}", Test::new(5)),
+            "0b00000000_1011_0000\n           aaa1_0bbb  a: first, b: _"
+        );
+    }
+}
@@ -11,8 +11,9 @@ mod test_get {
     }
 
     #[test]
+    #[allow(clippy::unusual_byte_groupings)]
     fn test() {
-        let header = IpHeader::from(0b0001_0010_00000011_0000000000000100).unwrap();
+        let header = IpHeader::try_from(0b0001_0010_00000011_0000000000000100).unwrap();
         assert_eq!(header.version(), 0b0001);
         assert_eq!(header.ihl(), 0b0010);
         assert_eq!(header.type_of_service(), 0b0011);
@@ -31,8 +32,9 @@ mod test_set {
     }
 
     #[test]
+    #[allow(clippy::unusual_byte_groupings)]
     fn test() {
-        let mut header = IpHeader::from(0b0001_0010_00000011_0000000000000100).unwrap();
+        let mut header = IpHeader::try_from(0b0001_0010_00000011_0000000000000100).unwrap();
         assert_eq!(header.version(), 0b0001);
         assert_eq!(header.ihl(), 0b0010);
         assert_eq!(header.type_of_service(), 0b0011);
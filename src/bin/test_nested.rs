@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod test_flags {
+    // The flags of RFC 791, where the first bit is reserved and must be 0
+    bitrange! {
+        Ipv4Flags: u3,
+        0dm,
+        d: dont_fragment set_dont_fragment,
+        m: more_fragments set_more_fragments
+    }
+
+    bitrange! {
+        Fragment: u16,
+        fff_ooooooooooooo,
+        f: flags set_flags as Ipv4Flags,
+        o: offset set_offset
+    }
+
+    #[test]
+    fn test() {
+        let mut fragment = Fragment::try_from(0x40b9).unwrap();
+        assert!(fragment.flags().dont_fragment());
+        assert!(!fragment.flags().more_fragments());
+        assert_eq!(fragment.offset(), 185);
+
+        fragment.set_flags(*Ipv4Flags::default().set_more_fragments(true));
+        assert_eq!(fragment.bits, 0x20b9);
+        assert_eq!(Fragment::new(Ipv4Flags::new(true, false), 185).bits, 0x40b9);
+        assert_eq!(
+            Fragment::builder()
+                .flags(Ipv4Flags::new(true, true))
+                .offset(1)
+                .build()
+                .bits,
+            0x6001
+        );
+
+        // The offsets of the nested struct are relative to the field
+        assert_eq!(1 << (Fragment::F_OFFSET + Ipv4Flags::D_OFFSET), 0x4000);
+    }
+
+    #[test]
+    fn test_fixed_bits() {
+        // The reserved bit of the flags is checked by the struct that has the flags
+        assert_eq!(Fragment::__bitrange_get_default_mask(), 0x8000);
+        let e = Fragment::try_from(0x80b9).unwrap_err();
+        assert_eq!(e.to_string(), "Fragment: bit 15 must be 0");
+        assert_eq!(Fragment::from_bits_masked(0xffff).bits, 0x7fff);
+        assert!(Fragment::try_from_bits(0x7fff).is_ok());
+    }
+
+    #[test]
+    fn test_formats() {
+        let fragment = Fragment::try_from(0x40b9).unwrap();
+        assert_eq!(
+            format!("{:?}", fragment),
+            "Fragment { flags: Ipv4Flags { dont_fragment: true, more_fragments: false }, offset: 185 }"
+        );
+        let diagram = fragment.to_string();
+        assert!(diagram.contains("|flags|         offset          |"));
+        assert!(diagram.contains("| 010 |           185           |"));
+    }
+}
+
+#[cfg(test)]
+mod test_recursive {
+    bitrange! {
+        Inner: u4,
+        1aaa,
+        a: value
+    }
+
+    bitrange! {
+        Middle: u8,
+        iiii_bbbb,
+        i: inner as Inner,
+        b: other
+    }
+
+    bitrange! {
+        Outer: u16,
+        mmmmmmmm_cccccccc,
+        m: middle set_middle as Middle,
+        c: other
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(Outer::default().bits, 0x8000);
+        assert_eq!(
+            Outer::try_from(0x0000).unwrap_err().to_string(),
+            "Outer: bit 15 must be 1"
+        );
+
+        let mut outer = Outer::try_from(0xd312).unwrap();
+        assert_eq!(outer.middle().inner().value(), 5);
+        assert_eq!(outer.middle().other(), 3);
+        assert_eq!(outer.other(), 0x12);
+
+        outer.set_middle(Middle::try_from(0xf0).unwrap());
+        assert_eq!(outer.bits, 0xf012);
+    }
+}
+
+#[cfg(test)]
+mod test_arrays {
+    bitrange! {
+        Port: [u8; 2],
+        1ppppppppppppppp,
+        p: number
+    }
+
+    bitrange! {
+        Ports: [u8; 4],
+        ssssssssssssssss_dddddddddddddddd,
+        s: source set_source as Port,
+        d: destination as Port
+    }
+
+    #[test]
+    fn test() {
+        let mut ports = Ports::try_from([0x80, 0x50, 0x81, 0xbb]).unwrap();
+        assert_eq!(ports.source().number(), 80);
+        assert_eq!(ports.destination().number(), 443);
+
+        ports.set_source(Port::try_from([0x9f, 0x90]).unwrap());
+        assert_eq!(ports.bits(), [0x9f, 0x90, 0x81, 0xbb]);
+
+        let e = Ports::try_from([0x80, 0x50, 0x01, 0xbb]).unwrap_err();
+        assert_eq!(e.to_string(), "Ports: bit 15 must be 1");
+        assert_eq!(Ports::default().bits(), [0x80, 0x00, 0x80, 0x00]);
+    }
+}
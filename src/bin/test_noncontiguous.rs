@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod test_interleaved {
+    bitrange! {
+        Test: u8,
+        abab_abab,
+        a: first set_first,
+        b: second set_second
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::try_from(0b1000_1101).unwrap();
+        assert_eq!(test.first(), 0b1010);
+        assert_eq!(test.second(), 0b0011);
+
+        test.set_first(0b0110);
+        assert_eq!(test.bits, 0b0010_1101);
+        assert_eq!(test.first(), 0b0110);
+        assert_eq!(test.second(), 0b0011);
+
+        test.set_second_wrapping(0b1111_1010);
+        assert_eq!(test.bits, 0b0110_1100);
+        assert_eq!(test.first(), 0b0110);
+        assert_eq!(test.second(), 0b1010);
+    }
+}
+
+#[cfg(test)]
+mod test_riscv_s_type {
+    // The S-type instruction encoding of RISC-V splits the immediate in two parts:
+    // imm[11:5] rs2 rs1 funct3 imm[4:0] opcode
+    bitrange! {
+        SType: u32,
+        iiiiiii_bbbbb_aaaaa_fff_iiiii_ooooooo,
+        i: imm set_imm,
+        b: rs2,
+        a: rs1,
+        f: funct3,
+        o: opcode
+    }
+
+    #[test]
+    #[allow(clippy::unusual_byte_groupings)]
+    fn test() {
+        // sw x5, 0x7ed(x6)
+        let mut instruction = SType::try_from(0b0111111_00101_00110_010_01101_0100011).unwrap();
+        assert_eq!(instruction.imm(), 0x7ed);
+        assert_eq!(instruction.rs2(), 5);
+        assert_eq!(instruction.rs1(), 6);
+        assert_eq!(instruction.funct3(), 0b010);
+        assert_eq!(instruction.opcode(), 0b0100011);
+
+        instruction.set_imm(0x123);
+        assert_eq!(instruction.bits, 0b0001001_00101_00110_010_00011_0100011);
+        assert_eq!(instruction.imm(), 0x123);
+        assert_eq!(instruction.rs2(), 5);
+        assert_eq!(instruction.opcode(), 0b0100011);
+    }
+}
+
+#[cfg(test)]
+mod test_riscv_b_type {
+    // The B-type instruction encoding of RISC-V shuffles the bits of the immediate,
+    // and bit 0 is not encoded because it is always 0:
+    // imm[12|10:5] rs2 rs1 funct3 imm[4:1|11] opcode
+    bitrange! {
+        BType: u32,
+        iiiiiii_bbbbb_aaaaa_fff_iiiii_ooooooo,
+        i: imm[12|10:5|4:1|11] set_imm signed,
+        b: rs2,
+        a: rs1,
+        f: funct3,
+        o: opcode
+    }
+
+    #[test]
+    #[allow(clippy::unusual_byte_groupings)]
+    fn test() {
+        // beq x1, x2, -8
+        let mut instruction = BType::try_from(0b1111111_00010_00001_000_11001_1100011).unwrap();
+        assert_eq!(instruction.imm(), -8);
+        assert_eq!(instruction.rs2(), 2);
+        assert_eq!(instruction.rs1(), 1);
+        assert_eq!(instruction.opcode(), 0b1100011);
+
+        instruction.set_imm(2046);
+        assert_eq!(instruction.bits, 0b0111111_00010_00001_000_11110_1100011);
+        instruction.set_imm(2048);
+        assert_eq!(instruction.bits, 0b0000000_00010_00001_000_00001_1100011);
+        assert_eq!(instruction.imm(), 2048);
+        instruction.set_imm(-4096);
+        assert_eq!(instruction.bits, 0b1000000_00010_00001_000_00000_1100011);
+        assert_eq!(instruction.imm(), -4096);
+        assert_eq!(instruction.rs2(), 2);
+        assert_eq!(instruction.opcode(), 0b1100011);
+
+        let instruction = BType::new(0x422, 2, 1, 0, 0b1100011);
+        assert_eq!(instruction.bits, 0b0100001_00010_00001_000_00010_1100011);
+        // The mask has the 12 bits in the pattern, the value has 13 bits because bit 0 is always 0
+        assert_eq!(BType::I_MASK, 0b1111111_00000_00000_000_11111_0000000);
+        assert_eq!(BType::I_WIDTH, 12);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut instruction = BType::default();
+        assert!(instruction.try_set_imm(4096).is_err());
+        assert!(instruction.try_set_imm(-4098).is_err());
+        // Bit 0 can not be stored
+        assert!(instruction.try_set_imm(3).is_err());
+        assert!(instruction.try_set_imm(-4096).is_ok());
+        assert_eq!(instruction.imm(), -4096);
+    }
+}
+
+#[cfg(test)]
+mod test_riscv_j_type {
+    // imm[20|10:1|11|19:12] rd opcode
+    bitrange! {
+        JType: u32,
+        iiiiiiiiiiiiiiiiiiii_ddddd_ooooooo,
+        i: imm[20|10:1|11|19:12] set_imm signed,
+        d: rd,
+        o: opcode
+    }
+
+    #[test]
+    #[allow(clippy::unusual_byte_groupings)]
+    fn test() {
+        // jal x1, 2048
+        let mut instruction = JType::try_from(0b00000000000100000000_00001_1101111).unwrap();
+        assert_eq!(instruction.imm(), 2048);
+        assert_eq!(instruction.rd(), 1);
+
+        instruction.set_imm(-2);
+        assert_eq!(instruction.bits, 0b11111111111111111111_00001_1101111);
+        assert_eq!(instruction.imm(), -2);
+        instruction.set_imm(0x7a2b6);
+        assert_eq!(instruction.bits, 0b00101011011001111010_00001_1101111);
+        assert_eq!(instruction.imm(), 0x7a2b6);
+        instruction.set_imm(-(1 << 20));
+        assert_eq!(instruction.bits, 0b10000000000000000000_00001_1101111);
+        assert_eq!(instruction.imm(), -(1 << 20));
+        assert_eq!(instruction.rd(), 1);
+        assert_eq!(instruction.opcode(), 0b1101111);
+    }
+}
+
+#[cfg(test)]
+mod test_riscv_c_lui {
+    // The immediate of the compressed `c.lui` has more bits than the 16 bits of the instruction:
+    // 011 imm[17] rd imm[16:12] 01
+    bitrange! {
+        CLui: u16,
+        011i_dddd_diii_ii01,
+        i: imm[17|16:12] set_imm signed,
+        d: rd
+    }
+
+    #[test]
+    fn test() {
+        // c.lui a0, 0xfffff
+        let mut instruction = CLui::try_from(0b0111_0101_0111_1101).unwrap();
+        assert_eq!(instruction.imm(), -4096);
+        assert_eq!(instruction.rd(), 10);
+
+        instruction.set_imm(0x1f000);
+        assert_eq!(instruction.bits, 0b0110_0101_0111_1101);
+        assert_eq!(instruction.imm(), 0x1f000);
+        instruction.set_imm(-(1 << 17));
+        assert_eq!(instruction.bits, 0b0111_0101_0000_0001);
+        assert_eq!(instruction.imm(), -(1 << 17));
+        assert_eq!(instruction.rd(), 10);
+
+        let instruction = CLui::builder().rd(10).imm(0x1000).build();
+        assert_eq!(instruction.bits, 0b0110_0101_0000_0101);
+        assert!(CLui::default().try_set_imm(0x800).is_err());
+    }
+}
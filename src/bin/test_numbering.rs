@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod test_lsb0 {
+    bitrange! {
+        Test: u16,
+        aaaa_bbbb_bbbb_0001,
+        a: first,
+        b: second
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!((Test::A_FIRST_BIT, Test::A_LAST_BIT), (15, 12));
+        assert_eq!((Test::B_FIRST_BIT, Test::B_LAST_BIT), (11, 4));
+        assert_eq!(Test::B_OFFSET, 4);
+
+        let e = Test::try_from(0x0008).unwrap_err();
+        assert_eq!(e.numbering, bitrange::BitNumbering::Lsb0);
+        assert_eq!(e.positions().collect::<Vec<_>>(), vec![3, 0]);
+        assert_eq!(e.to_string(), "Test: bit 3 must be 0, bit 0 must be 1");
+    }
+}
+
+#[cfg(test)]
+mod test_msb0 {
+    // The flags and the fragment offset of RFC 791, where bit 0 is the first bit of the header
+    bitrange! {
+        #[bitrange(bit_numbering = "msb0")]
+        Ipv4Header: [u8; 20],
+        aaaa_bbbb_cccccccc_dddddddddddddddd_eeeeeeeeeeeeeeee_0fgiiiiiiiiiiiii_jjjjjjjj_kkkkkkkk_llllllllllllllll_mmmmmmmmmmmmmmmmmmmmmmmmmmmmmmmm_nnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnn,
+        a: version,
+        b: ihl,
+        c: type_of_service,
+        d: total_length,
+        e: identification,
+        f: dont_fragment,
+        g: more_fragments,
+        i: fragment_offset,
+        j: time_to_live,
+        k: protocol,
+        l: header_checksum,
+        m: source,
+        n: destination
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!((Ipv4Header::A_FIRST_BIT, Ipv4Header::A_LAST_BIT), (0, 3));
+        assert_eq!((Ipv4Header::F_FIRST_BIT, Ipv4Header::F_LAST_BIT), (49, 49));
+        assert_eq!((Ipv4Header::I_FIRST_BIT, Ipv4Header::I_LAST_BIT), (51, 63));
+        assert_eq!(
+            (Ipv4Header::N_FIRST_BIT, Ipv4Header::N_LAST_BIT),
+            (128, 159)
+        );
+        // The offset is still the amount of bits the field is shifted
+        assert_eq!(Ipv4Header::I_OFFSET, 96);
+
+        let mut bytes = [0; 20];
+        bytes[6] = 0x80;
+        let e = Ipv4Header::try_from(bytes).unwrap_err();
+        assert_eq!(e.positions().collect::<Vec<_>>(), vec![48]);
+        assert_eq!(e.to_string(), "Ipv4Header: bit 48 must be 0");
+    }
+
+    #[test]
+    fn test_diagram() {
+        let header = Ipv4Header::default();
+        let diagram = header.to_string();
+        let mut lines = diagram.lines();
+        assert_eq!(
+            lines.next(),
+            Some(" 0                   1                   2                   3")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(" 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1")
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_msb0_width {
+    bitrange! {
+        #[bitrange(bit_numbering = "msb0")]
+        Register: u24,
+        r_aaaaaaa_dddddddddddddddd,
+        r: read,
+        a: address,
+        d: data
+    }
+
+    #[test]
+    fn test() {
+        // Bit 0 is the first bit of the 24 bits, not of the u32 they are stored in
+        assert_eq!((Register::R_FIRST_BIT, Register::D_LAST_BIT), (0, 23));
+
+        let e = Register::try_from(0x0100_0000).unwrap_err();
+        assert_eq!(e.positions().count(), 0);
+        assert_eq!(
+            e.to_string(),
+            "Register: the bits above the 24 bits must be 0"
+        );
+    }
+}
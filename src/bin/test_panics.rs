@@ -1,5 +1,4 @@
 #[test]
-#[cfg(not(feature = "panic"))]
 fn test_error() {
     bitrange! {
         Test: u8, "u8",
@@ -8,8 +7,8 @@ fn test_error() {
     }
     // Because the pattern is 1111_111
     // This means that all bits, except the last, should always be 1
-    // Because `0` does not match this criteria, this function panics
-    if let Err(e) = Test::from(0) {
+    // Because `0` does not match this criteria, this function returns an error
+    if let Err(e) = Test::try_from(0) {
         assert_eq!(0b1111_1110, e.expected);
         assert_eq!(0, e.provided);
     } else {
@@ -18,8 +17,39 @@ fn test_error() {
 }
 
 #[test]
-#[cfg(feature = "panic")]
-#[should_panic]
+fn test_error_message() {
+    bitrange! {
+        IpHeader: u32,
+        aaaa_bbbb_cccccccc_dd01_dddddddddddd,
+        a: version,
+        b: ihl,
+        c: type_of_service,
+        d: total_length
+    }
+
+    let e = IpHeader::try_from(0x4500_0000).unwrap_err();
+    assert_eq!(e.name, "IpHeader");
+    assert_eq!(e.mask, 0x0000_3000);
+    assert_eq!(e.xor, 0x0000_1000);
+    assert_eq!(e.positions().collect::<Vec<_>>(), vec![12]);
+    assert_eq!(e.to_string(), "IpHeader: bit 12 must be 1");
+
+    let e = IpHeader::try_from(0x4500_2000).unwrap_err();
+    assert_eq!(e.positions().collect::<Vec<_>>(), vec![13, 12]);
+    assert_eq!(
+        e.to_string(),
+        "IpHeader: bit 13 must be 0, bit 12 must be 1"
+    );
+
+    fn parse(bits: u32) -> Result<u8, Box<dyn std::error::Error>> {
+        Ok(IpHeader::try_from(bits)?.version())
+    }
+    assert_eq!(parse(0x4500_1000).unwrap(), 4);
+    assert!(parse(0x4500_0000).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Test: the bits that are always 0 or 1 do not match the pattern")]
 fn test_panic() {
     bitrange! {
         Test: u8, "u8",
@@ -29,5 +59,23 @@ fn test_panic() {
     // Because the pattern is 1111_111
     // This means that all bits, except the last, should always be 1
     // Because `0` does not match this criteria, this function panics
-    let _ = Test::from(0);
+    let _ = Test::from_bits_or_panic(0);
+}
+
+#[test]
+fn test_masked() {
+    bitrange! {
+        Test: u8,
+        aaa1_0bbb,
+        a: first,
+        b: second
+    }
+
+    const VALID: Test = Test::from_bits_or_panic(0b1011_0011);
+    assert_eq!(VALID.bits, 0b1011_0011);
+
+    // The bits that are always 0 or 1 are set to their value
+    const MASKED: Test = Test::from_bits_masked(0b1010_1011);
+    assert_eq!(MASKED.bits, 0b1011_0011);
+    assert_eq!(Test::try_from_bits(0b1011_0011), Ok(VALID));
 }
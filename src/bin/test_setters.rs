@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod test_overflow {
+    use bitrange::FieldOverflow;
+
+    bitrange! {
+        Test: u16,
+        aaaa_bbbb_bbbb_cccc,
+        a: first set_first,
+        b: second set_second as u32,
+        c: third set_third signed
+    }
+
+    #[test]
+    fn test_try() {
+        let mut test = Test::default();
+        test.try_set_first(0xf)
+            .unwrap()
+            .try_set_second(0xff)
+            .unwrap();
+        assert_eq!(test.bits, 0xfff0);
+
+        assert_eq!(
+            test.try_set_first(0x10).err(),
+            Some(FieldOverflow {
+                field: "first",
+                bits: 4
+            })
+        );
+        assert!(test.try_set_second(0x100).is_err());
+        assert!(test.try_set_third(8).is_err());
+        assert!(test.try_set_third(-9).is_err());
+        assert_eq!(test.bits, 0xfff0);
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let mut test = Test::default();
+        test.set_first_wrapping(0x12)
+            .set_second_wrapping(0x1234)
+            .set_third_wrapping(-9);
+        assert_eq!(test.bits, 0x2347);
+        assert_eq!(test.third(), 7);
+    }
+
+    #[test]
+    fn test_saturating() {
+        let mut test = Test::default();
+        test.set_first_saturating(0x12)
+            .set_second_saturating(0x1234)
+            .set_third_saturating(-9);
+        assert_eq!(test.bits, 0xfff8);
+        assert_eq!(test.third(), -8);
+
+        test.set_third_saturating(100);
+        assert_eq!(test.third(), 7);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Test: value does not fit in the 4 bits of field `first`")]
+    fn test_overflow() {
+        Test::default().set_first(0x10);
+    }
+}
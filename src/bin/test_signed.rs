@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod test_contiguous {
+    use bitrange::FieldOverflow;
+
+    bitrange! {
+        Test: u16,
+        aaaa_bbbb_bbbb_cccc,
+        a: offset set_offset signed,
+        b: reading set_reading as i16,
+        c: flags
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::try_from(0xf7f1).unwrap();
+        let offset: i8 = test.offset();
+        assert_eq!(offset, -1);
+        assert_eq!(test.reading(), 0x7f);
+        assert_eq!(test.flags(), 0x1);
+
+        test.set_offset(7).set_reading(-128);
+        assert_eq!(test.bits, 0x7801);
+        assert_eq!(test.offset(), 7);
+        assert_eq!(test.reading(), -128);
+
+        test.set_offset(-8);
+        assert_eq!(test.bits, 0x8801);
+        assert_eq!(test.offset(), -8);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut test = Test::default();
+        assert_eq!(
+            test.try_set_offset(8).err(),
+            Some(FieldOverflow {
+                field: "offset",
+                bits: 4
+            })
+        );
+        assert!(test.try_set_offset(-9).is_err());
+        assert!(test.try_set_reading(128).is_err());
+        assert!(test.try_set_reading(-129).is_err());
+        assert_eq!(test.bits, 0);
+    }
+}
+
+#[cfg(test)]
+mod test_noncontiguous {
+    // The immediate of a RISC-V store is split in two
+    bitrange! {
+        SType: u32,
+        iiiiiii_bbbbb_aaaaa_fff_iiiii_ooooooo,
+        i: imm set_imm signed,
+        b: rs2,
+        a: rs1,
+        f: funct3,
+        o: opcode
+    }
+
+    #[test]
+    fn test() {
+        // sw x2, -4(x1)
+        let mut store = SType::try_from(0xfe20_ae23).unwrap();
+        let imm: i16 = store.imm();
+        assert_eq!(imm, -4);
+        assert_eq!((store.rs2(), store.rs1(), store.funct3()), (2, 1, 2));
+
+        store.set_imm(8);
+        assert_eq!(store.bits, 0x0020_a423);
+        assert_eq!(store.imm(), 8);
+
+        store.set_imm(-2048);
+        assert_eq!(store.bits, 0x8020_a023);
+        assert_eq!(store.imm(), -2048);
+
+        assert!(store.try_set_imm(2048).is_err());
+        assert!(store.try_set_imm(-2049).is_err());
+        assert_eq!(store.imm(), -2048);
+    }
+}
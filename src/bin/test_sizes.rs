@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod test_u128 {
+    bitrange! {
+        Test: u128,
+        aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa_bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb_cccccccc_1111,
+        a: high,
+        b: middle set_middle,
+        c: low
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::try_from(0x0123_4567_89ab_cdef_fedc_ba98_7654_321f).unwrap();
+        assert_eq!(test.high(), 0x0123_4567_89ab_cdef);
+        assert_eq!(test.middle(), 0x000f_edcb_a987_6543);
+        assert_eq!(test.low(), 0x21);
+
+        // The middle field crosses the 64th bit
+        test.set_middle(0x000a_aaaa_aaaa_aaaa);
+        assert_eq!(test.bits(), 0x0123_4567_89ab_cdef_aaaa_aaaa_aaaa_a21f);
+        assert_eq!(test.to_be_bytes()[15], 0x1f);
+        assert_eq!(Test::from_le_bytes(test.to_le_bytes()), Ok(test));
+
+        let e = Test::try_from(0).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Test: bit 3 must be 1, bit 2 must be 1, bit 1 must be 1, bit 0 must be 1"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_u24 {
+    use bitrange::BytesError;
+
+    // A 24-bit register of an SPI device: a read bit, a 7-bit address and 16 bits of data
+    bitrange! {
+        Register: u24,
+        r_aaaaaaa_dddddddddddddddd,
+        r: read,
+        a: address,
+        d: data set_data
+    }
+
+    #[test]
+    fn test() {
+        let mut register = Register::try_from(&[0x85, 0x12, 0x34][..]).unwrap();
+        assert!(register.read());
+        assert_eq!(register.address(), 5);
+        assert_eq!(register.data(), 0x1234);
+
+        // The bits are stored in a u32
+        let bits: u32 = register.bits();
+        assert_eq!(bits, 0x0085_1234);
+
+        register.set_data(0xabcd);
+        assert_eq!(register.to_be_bytes(), [0x85, 0xab, 0xcd]);
+        assert_eq!(register.to_le_bytes(), [0xcd, 0xab, 0x85]);
+        assert_eq!(Register::from_le_bytes([0xcd, 0xab, 0x85]), Ok(register));
+        assert_eq!(
+            Register::from_ne_bytes(register.to_ne_bytes()),
+            Ok(register)
+        );
+        assert_eq!(<[u8; 3]>::from(register), [0x85, 0xab, 0xcd]);
+
+        assert_eq!(
+            Register::try_from(&[0x85, 0xab, 0xcd, 0x00][..]),
+            Err(BytesError::Length {
+                name: "Register",
+                expected: 3,
+                provided: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_high_bits() {
+        // The bits above the 24 bits are always 0
+        let e = Register::try_from(0x0100_0000).unwrap_err();
+        assert_eq!(e.to_string(), "Register: bit 24 must be 0");
+        assert_eq!(Register::from_bits_masked(0xff85_1234).bits(), 0x0085_1234);
+        assert_eq!(Register::default().bits(), 0);
+    }
+}
+
+#[cfg(test)]
+mod test_u48 {
+    // The lowest 2 bits of the first byte are flags, the other 22 bits of the first 3 bytes are the OUI
+    bitrange! {
+        MacAddress: u48,
+        oooooo_lm_oooooooooooooooo_nnnnnnnnnnnnnnnnnnnnnnnn,
+        o: oui,
+        l: local,
+        m: multicast,
+        n: nic
+    }
+
+    #[test]
+    fn test() {
+        let mac = MacAddress::from_be_bytes([0x00, 0x1b, 0x63, 0x84, 0x45, 0xe6]).unwrap();
+        assert_eq!(mac.oui(), 0x1b63);
+        assert!(!mac.local());
+        assert!(!mac.multicast());
+        assert_eq!(mac.nic(), 0x8445e6);
+        assert_eq!(mac.bits(), 0x001b_6384_45e6_u64);
+        assert_eq!(mac.to_be_bytes(), [0x00, 0x1b, 0x63, 0x84, 0x45, 0xe6]);
+        assert_eq!(format!("{:x}", mac), "1b638445e6");
+    }
+}
+
+#[cfg(test)]
+mod test_u12 {
+    bitrange! {
+        Test: u12,
+        aaaa_bbbb_cccc,
+        a: first,
+        b: second,
+        c: third
+    }
+
+    #[test]
+    fn test() {
+        // 12 bits fit in 2 bytes, just like the u16 they are stored in
+        let test = Test::from_be_bytes([0x0a, 0xbc]).unwrap();
+        assert_eq!((test.first(), test.second(), test.third()), (0xa, 0xb, 0xc));
+        assert_eq!(test.to_le_bytes(), [0xbc, 0x0a]);
+        assert!(Test::from_be_bytes([0x1a, 0xbc]).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod test_unquoted {
+    bitrange! {
+        /// An IP header with an unquoted pattern
+        IpHeader: u32,
+        aaaa_bbbb_cccccccc_dddddddddddddddd,
+        a: version set_version,
+        b: ihl,
+        c: type_of_service,
+        d: total_length
+    }
+
+    #[test]
+    fn test() {
+        let mut header = IpHeader::try_from(0b0001_0010_0000_0011_0000_0000_0000_0100).unwrap();
+        assert_eq!(header.version(), 0b0001);
+        assert_eq!(header.ihl(), 0b0010);
+        assert_eq!(header.type_of_service(), 0b0011);
+        assert_eq!(header.total_length(), 0b0100);
+
+        header.set_version(0b0100);
+        assert_eq!(header.version(), 0b0100);
+    }
+}
+
+#[cfg(test)]
+mod test_literal_pattern {
+    bitrange! {
+        Test: u8,
+        1111_aaaa,
+        a: first
+    }
+
+    #[test]
+    fn test() {
+        let test = Test::default();
+        assert_eq!(test.bits, 0b1111_0000);
+        assert_eq!(Test::try_from(0b1111_0101).unwrap().first(), 0b0101);
+    }
+}
+
+#[cfg(test)]
+mod test_short {
+    bitrange! {
+        #[bitrange(short)]
+        Test: u16,
+        1aaa_bbbb,
+        a: first,
+        b: second set_second
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::default();
+        assert_eq!(test.bits, 0b1000_0000);
+
+        test.set_second(0b1010);
+        assert_eq!(test.bits, 0b1000_1010);
+
+        let test = Test::try_from(0b1111_1111_1011_0001).unwrap();
+        assert_eq!(test.first(), 0b011);
+        assert_eq!(test.second(), 0b0001);
+    }
+}
+
+#[cfg(test)]
+mod test_repeat {
+    bitrange! {
+        Test: u16,
+        a{4}_1{2}b{10},
+        a: first,
+        b: second set_second
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(Test::default().bits, 0b0000_1100_0000_0000);
+        assert_eq!((Test::A_WIDTH, Test::B_WIDTH), (4, 10));
+
+        let mut test = Test::try_from(0b1010_1100_0000_0001).unwrap();
+        assert_eq!(test.first(), 0b1010);
+        assert_eq!(test.second(), 1);
+        test.set_second(0x3ff);
+        assert_eq!(test.bits, 0b1010_1111_1111_1111);
+    }
+}
+
+#[cfg(test)]
+mod test_widths {
+    bitrange! {
+        /// The first word of an IP header, declared by the amount of bits of every field
+        IpHeader: u32,
+        version: 4 set_version,
+        ihl: 4,
+        type_of_service: 8,
+        _: 0b01,
+        total_length: 14 set_total_length
+    }
+
+    #[test]
+    fn test() {
+        let mut header = IpHeader::try_from(0x4500_4014).unwrap();
+        assert_eq!(header.version(), 4);
+        assert_eq!(header.ihl(), 5);
+        assert_eq!(header.type_of_service(), 0);
+        assert_eq!(header.total_length(), 0x14);
+
+        header.set_total_length(0x3fff).set_version(6);
+        assert_eq!(header.bits, 0x6500_7fff);
+        assert_eq!(IpHeader::default().bits, 0x0000_4000);
+        assert_eq!(
+            IpHeader::try_from(0x4500_0014).unwrap_err().to_string(),
+            "IpHeader: bit 14 must be 1"
+        );
+
+        // The associated constants are named after the fields
+        assert_eq!(IpHeader::VERSION_OFFSET, 28);
+        assert_eq!(IpHeader::TYPE_OF_SERVICE_MASK, 0x00ff_0000);
+        assert_eq!(IpHeader::TOTAL_LENGTH_WIDTH, 14);
+    }
+}
+
+#[cfg(test)]
+mod test_widths_noncontiguous {
+    // The S-type instructions of RISC-V, where `imm` is split in two and funct3 is reserved
+    bitrange! {
+        SType: u32,
+        imm: 7 set_imm signed,
+        rs2: 5,
+        rs1: 5,
+        _: 3,
+        imm: 5,
+        opcode: 7
+    }
+
+    #[test]
+    fn test() {
+        let bits = 0x7f << 25 | 2 << 20 | 1 << 15 | 2 << 12 | 0x1c << 7 | 0x23;
+        let mut instruction = SType::try_from(bits).unwrap();
+        assert_eq!(instruction.imm(), -4);
+        assert_eq!(instruction.rs2(), 2);
+        assert_eq!(instruction.rs1(), 1);
+        assert_eq!(instruction.opcode(), 0x23);
+        assert_eq!((SType::RESERVED_OFFSET, SType::RESERVED_WIDTH), (12, 3));
+        assert_eq!(SType::IMM_WIDTH, 12);
+
+        instruction.set_imm(8);
+        assert_eq!(instruction.imm(), 8);
+        assert_eq!(
+            instruction.bits,
+            2 << 20 | 1 << 15 | 2 << 12 | 8 << 7 | 0x23
+        );
+    }
+}
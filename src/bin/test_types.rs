@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod test_smallest_type {
+    bitrange! {
+        Test: u64,
+        aaaa_bbbbbbbbbbbb_cccccccccccccccccccc_dddddddddddddddddddddddddddd,
+        a: first set_first,
+        b: second set_second,
+        c: third set_third,
+        d: fourth
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::default();
+        let first: u8 = test.first();
+        let second: u16 = test.second();
+        let third: u32 = test.third();
+        let fourth: u32 = test.fourth();
+        assert_eq!((first, second, third, fourth), (0, 0, 0, 0));
+
+        test.set_first(0xf_u8)
+            .set_second(0xabc_u16)
+            .set_third(0x12345_u32);
+        assert_eq!(test.bits, 0xfabc_1234_5000_0000);
+        assert_eq!(test.first(), 0xf);
+        assert_eq!(test.second(), 0xabc);
+        assert_eq!(test.third(), 0x12345);
+    }
+}
+
+#[cfg(test)]
+mod test_override {
+    bitrange! {
+        Test: u16,
+        aaaa_bbbb_bbbb_bbbb,
+        a: first set_first as u32,
+        b: second as u16
+    }
+
+    #[test]
+    fn test() {
+        let mut test = Test::try_from(0x1234).unwrap();
+        let first: u32 = test.first();
+        let second: u16 = test.second();
+        assert_eq!(first, 0x1);
+        assert_eq!(second, 0x234);
+
+        test.set_first(0x5_u32);
+        assert_eq!(test.bits, 0x5234);
+    }
+}
+
+#[cfg(test)]
+mod test_flags {
+    bitrange! {
+        #[bitrange(short)]
+        Ipv4Flags: u8,
+        0dm,
+        d: dont_fragment set_dont_fragment,
+        m: more_fragments set_more_fragments as u8
+    }
+
+    #[test]
+    fn test() {
+        let mut flags = Ipv4Flags::try_from(0b010).unwrap();
+        let dont_fragment: bool = flags.dont_fragment();
+        let more_fragments: u8 = flags.more_fragments();
+        assert!(dont_fragment);
+        assert_eq!(more_fragments, 0);
+
+        flags.set_dont_fragment(false).set_more_fragments(1);
+        assert_eq!(flags.bits, 0b001);
+
+        flags.toggle_dont_fragment();
+        assert!(flags.dont_fragment());
+        flags.toggle_dont_fragment();
+        assert!(!flags.dont_fragment());
+
+        flags.set_dont_fragment_on();
+        assert_eq!(flags.bits, 0b011);
+        flags.clear_dont_fragment();
+        assert_eq!(flags.bits, 0b001);
+    }
+}
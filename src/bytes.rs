@@ -0,0 +1,93 @@
+//! Operations on the `[u8; N]` storage of a bitrange struct, which are used by the generated code.
+//! The first byte holds the most significant bits, so these are the operations of a big-endian integer of `N * 8` bits.
+//! These are const fns, because the operators of an integer can not be implemented for an array.
+
+pub const fn and<const N: usize>(mut a: [u8; N], b: [u8; N]) -> [u8; N] {
+    let mut i = 0;
+    while i < N {
+        a[i] &= b[i];
+        i += 1;
+    }
+    a
+}
+
+pub const fn or<const N: usize>(mut a: [u8; N], b: [u8; N]) -> [u8; N] {
+    let mut i = 0;
+    while i < N {
+        a[i] |= b[i];
+        i += 1;
+    }
+    a
+}
+
+pub const fn xor<const N: usize>(mut a: [u8; N], b: [u8; N]) -> [u8; N] {
+    let mut i = 0;
+    while i < N {
+        a[i] ^= b[i];
+        i += 1;
+    }
+    a
+}
+
+pub const fn not<const N: usize>(mut a: [u8; N]) -> [u8; N] {
+    let mut i = 0;
+    while i < N {
+        a[i] = !a[i];
+        i += 1;
+    }
+    a
+}
+
+pub const fn eq<const N: usize>(a: [u8; N], b: [u8; N]) -> bool {
+    let mut i = 0;
+    while i < N {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Reverse the order of the bytes, to convert between big-endian and little-endian
+pub const fn reverse<const N: usize>(mut a: [u8; N]) -> [u8; N] {
+    let mut i = 0;
+    while i < N / 2 {
+        let byte = a[i];
+        a[i] = a[N - 1 - i];
+        a[N - 1 - i] = byte;
+        i += 1;
+    }
+    a
+}
+
+/// Convert between big-endian and the byte order of the target
+pub const fn native<const N: usize>(a: [u8; N]) -> [u8; N] {
+    if cfg!(target_endian = "little") {
+        reverse(a)
+    } else {
+        a
+    }
+}
+
+/// Convert the bytes to an integer, for a struct of at most 16 bytes that is the field of another struct
+pub const fn to_u128<const N: usize>(a: [u8; N]) -> u128 {
+    let mut value = 0;
+    let mut i = 0;
+    while i < N {
+        value = value << 8 | a[i] as u128;
+        i += 1;
+    }
+    value
+}
+
+/// Convert the lowest `N * 8` bits of an integer to bytes, the opposite of `to_u128`
+pub const fn from_u128<const N: usize>(value: u128) -> [u8; N] {
+    let mut a = [0; N];
+    let mut i = 0;
+    while i < N {
+        a[N - 1 - i] = (value >> (i * 8)) as u8;
+        i += 1;
+    }
+    a
+}
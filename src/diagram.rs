@@ -0,0 +1,352 @@
+use crate::{BitNumbering, Bits};
+use core::fmt::{self, Display, Formatter, Write};
+
+/// A run of consecutive bits in the diagram of a bitrange struct, starting at the most significant bit
+#[doc(hidden)]
+pub struct DiagramCell {
+    /// The name of the field, or an empty string for bits that are always 0 or 1 and reserved bits
+    pub name: &'static str,
+    /// The amount of bits in this cell
+    pub bits: usize,
+}
+
+/// The amount of bits in every row of a `Diagram`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowBits {
+    /// 8 bits in every row, for a diagram of bytes
+    B8,
+    /// 16 bits in every row
+    B16,
+    /// 32 bits in every row, like the diagrams in the RFCs
+    B32,
+}
+
+impl RowBits {
+    /// Returns the amount of bits in every row
+    pub const fn bits(self) -> usize {
+        match self {
+            RowBits::B8 => 8,
+            RowBits::B16 => 16,
+            RowBits::B32 => 32,
+        }
+    }
+}
+
+/// The amount of bits given to `RowBits::try_from` is not 8, 16 or 32
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidRowBits {
+    /// The amount of bits that was given
+    pub bits: usize,
+}
+
+impl InvalidRowBits {
+    pub const fn new(bits: usize) -> InvalidRowBits {
+        InvalidRowBits { bits }
+    }
+}
+
+/// Returns the `RowBits` with the given amount of bits, or an `InvalidRowBits` for any other amount than 8, 16 or 32
+impl TryFrom<usize> for RowBits {
+    type Error = InvalidRowBits;
+
+    fn try_from(bits: usize) -> Result<RowBits, InvalidRowBits> {
+        match bits {
+            8 => Ok(RowBits::B8),
+            16 => Ok(RowBits::B16),
+            32 => Ok(RowBits::B32),
+            _ => Err(InvalidRowBits::new(bits)),
+        }
+    }
+}
+
+/// An RFC style diagram of the fields of a bitrange struct, created with `to_diagram(row_bits)`.
+///
+/// ```text
+///  0                   1
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |version|  ihl  |type_of_service|
+/// |   4   |   5   |       0       |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |         total_length          |
+/// |             1500              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// Every bit is two characters wide, and the name and the value of a field are centered in its cell.
+/// Names and values that do not fit in the cell are cut off.
+/// Fields that do not fit in the rest of a row continue on the next row.
+///
+/// The header has the index of every bit of the first row, in the `BitNumbering` of the struct.
+/// The diagram above is of a struct with `BitNumbering::Msb0`, with `BitNumbering::Lsb0` the header counts down to 0.
+pub struct Diagram<'a, T> {
+    value: &'a T,
+    cells: &'static [DiagramCell],
+    fmt_cell: fn(&T, usize, &mut Formatter) -> fmt::Result,
+    row_bits: RowBits,
+    numbering: BitNumbering,
+    width: usize,
+}
+
+impl<'a, T> Diagram<'a, T> {
+    #[doc(hidden)]
+    pub fn new(
+        value: &'a T,
+        cells: &'static [DiagramCell],
+        fmt_cell: fn(&T, usize, &mut Formatter) -> fmt::Result,
+        row_bits: RowBits,
+        numbering: BitNumbering,
+        width: usize,
+    ) -> Diagram<'a, T> {
+        Diagram {
+            value,
+            cells,
+            fmt_cell,
+            row_bits,
+            numbering,
+            width,
+        }
+    }
+
+    /// Write the index of every bit in the first row.
+    /// The pattern is aligned to the least significant bits, so its first bit is bit `bits - 1` of the internal integer
+    fn write_header(&self, f: &mut Formatter, bits: usize, row_bits: usize) -> fmt::Result {
+        let index = |column: usize| {
+            self.numbering
+                .index(bits - 1 - column, self.width)
+                .unwrap_or_default()
+        };
+        // The tens are only written above every 10th bit, so the line stops at the last one
+        let last_ten = (0..row_bits).rev().find(|column| index(*column) % 10 == 0);
+        match last_ten {
+            Some(last_ten) if (0..row_bits).any(|column| index(column) >= 10) => {
+                for column in 0..=last_ten {
+                    if index(column) % 10 == 0 {
+                        write!(f, " {}", index(column) / 10 % 10)?;
+                    } else {
+                        f.write_str("  ")?;
+                    }
+                }
+                f.write_char('\n')?;
+            }
+            _ => {}
+        }
+        for column in 0..row_bits {
+            write!(f, " {}", index(column) % 10)?;
+        }
+        f.write_char('\n')
+    }
+
+    fn write_border(f: &mut Formatter, bits: usize) -> fmt::Result {
+        for _ in 0..bits {
+            f.write_str("+-")?;
+        }
+        f.write_char('+')
+    }
+
+    /// Write a line with a cell for every part of a field between bit `start` and `end`
+    fn write_line(
+        &self,
+        f: &mut Formatter,
+        start: usize,
+        end: usize,
+        content: impl Fn(usize) -> CellContent<'a, T>,
+    ) -> fmt::Result {
+        f.write_char('|')?;
+        let mut offset = 0;
+        for (index, cell) in self.cells.iter().enumerate() {
+            let from = if offset > start { offset } else { start };
+            let to = if offset + cell.bits < end {
+                offset + cell.bits
+            } else {
+                end
+            };
+            offset += cell.bits;
+            if from < to {
+                write_centered(f, 2 * (to - from) - 1, &content(index))?;
+                f.write_char('|')?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T> Display for Diagram<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let bits = self.cells.iter().map(|cell| cell.bits).sum::<usize>();
+        let row_bits = if self.row_bits.bits() < bits {
+            self.row_bits.bits()
+        } else {
+            bits
+        };
+        self.write_header(f, bits, row_bits)?;
+        Diagram::<T>::write_border(f, row_bits)?;
+        let mut start = 0;
+        while start < bits {
+            let end = if start + row_bits < bits {
+                start + row_bits
+            } else {
+                bits
+            };
+            f.write_char('\n')?;
+            self.write_line(f, start, end, |index| {
+                CellContent::Name(self.cells[index].name)
+            })?;
+            f.write_char('\n')?;
+            self.write_line(f, start, end, |index| {
+                CellContent::Value(self.value, index, self.fmt_cell)
+            })?;
+            f.write_char('\n')?;
+            Diagram::<T>::write_border(f, end - start)?;
+            start = end;
+        }
+        Ok(())
+    }
+}
+
+enum CellContent<'a, T> {
+    Name(&'static str),
+    Value(&'a T, usize, fn(&T, usize, &mut Formatter) -> fmt::Result),
+}
+
+impl<'a, T> Display for CellContent<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            CellContent::Name(name) => f.write_str(name),
+            CellContent::Value(value, index, fmt_cell) => fmt_cell(value, index, f),
+        }
+    }
+}
+
+/// Write `bits`, grouped with the underscores in `pattern`,
+/// and the pattern and the `legend` with the name of every field below them:
+///
+/// ```text
+/// 0b0100_0101_00000000_0000010111011100
+///   aaaa_bbbb_cccccccc_dddddddddddddddd  a: version, b: ihl, c: type_of_service, d: total_length
+/// ```
+///
+/// Only the lowest `width` bits are written, so the bits above a width like `u24` are left out.
+/// If the pattern has less bits than `width`, the bits above the pattern are written in a separate group.
+#[doc(hidden)]
+pub fn fmt_pattern_bits<T: Bits>(
+    bits: T,
+    width: usize,
+    pattern: &str,
+    legend: &str,
+    f: &mut Formatter,
+) -> fmt::Result {
+    let pattern_bits = pattern.chars().filter(|c| *c != '_').count();
+    let mut bit = width;
+    let mut write_bit = |f: &mut Formatter| {
+        bit -= 1;
+        f.write_char(if bits.bit(bit) { '1' } else { '0' })
+    };
+
+    f.write_str("0b")?;
+    for _ in pattern_bits..width {
+        write_bit(f)?;
+    }
+    if pattern_bits < width {
+        f.write_char('_')?;
+    }
+    for c in pattern.chars() {
+        if c == '_' {
+            f.write_char('_')?;
+        } else {
+            write_bit(f)?;
+        }
+    }
+
+    f.write_str("\n  ")?;
+    for _ in pattern_bits..width {
+        f.write_char(' ')?;
+    }
+    if pattern_bits < width {
+        f.write_char(' ')?;
+    }
+    f.write_str(pattern)?;
+    if !legend.is_empty() {
+        write!(f, "  {}", legend)?;
+    }
+    Ok(())
+}
+
+/// Write `bits` with `digit_bits` bits in every digit, like `{:b}`, `{:o}` and `{:x}` of an integer.
+/// This is used for the `[u8; N]` storage, which does not implement these traits itself
+#[doc(hidden)]
+pub fn fmt_bits<T: Bits>(
+    bits: T,
+    digit_bits: usize,
+    upper: bool,
+    prefix: &str,
+    f: &mut Formatter,
+) -> fmt::Result {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    if f.alternate() {
+        f.write_str(prefix)?;
+    }
+    let mut leading = true;
+    for digit in (0..T::BITS.div_ceil(digit_bits)).rev() {
+        let mut value = 0;
+        for bit in (digit * digit_bits..(digit + 1) * digit_bits).rev() {
+            value <<= 1;
+            if bit < T::BITS && bits.bit(bit) {
+                value |= 1;
+            }
+        }
+        // Leading zeros are skipped, except for the last digit
+        if leading && value == 0 && digit > 0 {
+            continue;
+        }
+        leading = false;
+        let c = DIGITS[value] as char;
+        f.write_char(if upper { c.to_ascii_uppercase() } else { c })?;
+    }
+    Ok(())
+}
+
+/// Write `content` centered in `width` characters, or only the first `width` characters if it does not fit
+fn write_centered(f: &mut Formatter, width: usize, content: &dyn Display) -> fmt::Result {
+    let mut counter = Counter(0);
+    write!(counter, "{}", content)?;
+    if counter.0 >= width {
+        return write!(Truncate(f, width), "{}", content);
+    }
+    let left = (width - counter.0) / 2;
+    let right = width - counter.0 - left;
+    for _ in 0..left {
+        f.write_char(' ')?;
+    }
+    write!(f, "{}", content)?;
+    for _ in 0..right {
+        f.write_char(' ')?;
+    }
+    Ok(())
+}
+
+/// Counts the characters that are written to it
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.chars().count();
+        Ok(())
+    }
+}
+
+/// Writes at most the given amount of characters to the formatter, and drops the rest
+struct Truncate<'a, 'b>(&'a mut Formatter<'b>, usize);
+
+impl<'a, 'b> Write for Truncate<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.1 == 0 {
+                break;
+            }
+            self.0.write_char(c)?;
+            self.1 -= 1;
+        }
+        Ok(())
+    }
+}
@@ -1,51 +1,321 @@
 #![deny(warnings)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
-#[cfg(feature = "panic")]
 mod error {
-    #[cfg(not(feature = "std"))]
-    use core::fmt::Binary;
-    #[cfg(not(feature = "std"))]
-    use core::marker::PhantomData;
-    #[cfg(feature = "std")]
-    use std::fmt::Binary;
-    #[cfg(feature = "std")]
-    use std::marker::PhantomData;
-
-    #[derive(Debug)]
+    use super::{BitNumbering, Bits};
+    use core::fmt;
+
+    /// The bits that are always 0 or 1 in the pattern of a bitrange struct do not match the given bits
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct Error<T> {
-        _phantomdata: PhantomData<T>,
-    }
-
-    impl<T> Error<T>
-    where
-        T: Binary,
-    {
-        pub fn invalid_bits(expected: T, provided: T) -> Error<T> {
-            panic!(
-                "Invalid bits, expected 0b{:0b}, got 0b{:0b}",
-                expected, provided
-            );
+        /// The name of the struct
+        pub name: &'static str,
+        /// The bits that are always 0 or 1
+        pub mask: T,
+        /// The value of the bits in `mask`
+        pub expected: T,
+        /// The given bits, masked with `mask`
+        pub provided: T,
+        /// The bits that do not match, `expected ^ provided`
+        pub xor: T,
+        /// The numbering of the bits in `positions()` and the error message
+        pub numbering: BitNumbering,
+        /// The amount of bits of the pattern, e.g. 24 for a `u24` that is stored in a `u32`
+        pub width: usize,
+    }
+
+    impl<T: Bits> Error<T> {
+        pub const fn invalid_bits(
+            name: &'static str,
+            mask: T,
+            expected: T,
+            provided: T,
+            xor: T,
+        ) -> Error<T> {
+            Error {
+                name,
+                mask,
+                expected,
+                provided,
+                xor,
+                numbering: BitNumbering::Lsb0,
+                width: T::BITS,
+            }
+        }
+
+        /// Set the numbering of the bits, and the amount of bits of the pattern
+        pub const fn with_numbering(mut self, numbering: BitNumbering, width: usize) -> Error<T> {
+            self.numbering = numbering;
+            self.width = width;
+            self
+        }
+
+        /// Returns the index of every bit that does not match, from the first to the last bit of the pattern.
+        ///
+        /// With `BitNumbering::Lsb0`, bit 0 is the least significant bit.
+        /// With `BitNumbering::Msb0`, bit 0 is the most significant bit of the pattern,
+        /// and the bits above a width like `u24` have no index, so they are only in `xor`
+        pub fn positions(&self) -> impl Iterator<Item = usize> {
+            let xor = self.xor;
+            let numbering = self.numbering;
+            let width = self.width;
+            (0..T::BITS)
+                .rev()
+                .filter(move |bit| xor.bit(*bit))
+                .filter_map(move |bit| numbering.index(bit, width))
+        }
+
+        /// Returns true if a bit above a width like `u24` does not match
+        fn above_width(&self) -> bool {
+            (self.width..T::BITS).any(|bit| self.xor.bit(bit))
+        }
+    }
+
+    /// Prints every bit that does not match, e.g. `IpHeader: bit 12 must be 1, bit 3 must be 0`
+    impl<T: Bits> fmt::Display for Error<T> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.name)?;
+            let mut separator = ": ";
+            if self.numbering == BitNumbering::Msb0 && self.above_width() {
+                write!(
+                    f,
+                    "{}the bits above the {} bits must be 0",
+                    separator, self.width
+                )?;
+                separator = ", ";
+            }
+            for bit in (0..T::BITS).rev().filter(|bit| self.xor.bit(*bit)) {
+                let index = match self.numbering.index(bit, self.width) {
+                    Some(index) => index,
+                    None => continue,
+                };
+                let expected = if self.expected.bit(bit) { 1 } else { 0 };
+                write!(f, "{}bit {} must be {}", separator, index, expected)?;
+                separator = ", ";
+            }
+            Ok(())
+        }
+    }
+
+    impl<T: Bits + fmt::Debug> core::error::Error for Error<T> {}
+
+    /// The bytes given to `TryFrom<&[u8]>` can not be converted to a bitrange struct
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum BytesError<T> {
+        /// The slice does not have as many bytes as the internal integer
+        Length {
+            /// The name of the struct
+            name: &'static str,
+            expected: usize,
+            provided: usize,
+        },
+        /// The bits that are always 0 or 1 do not match the pattern
+        InvalidBits(Error<T>),
+    }
+
+    impl<T> From<Error<T>> for BytesError<T> {
+        fn from(error: Error<T>) -> BytesError<T> {
+            BytesError::InvalidBits(error)
         }
     }
+
+    impl<T: Bits> fmt::Display for BytesError<T> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                BytesError::Length {
+                    name,
+                    expected,
+                    provided,
+                } => write!(f, "{}: expected {} bytes, got {}", name, expected, provided),
+                BytesError::InvalidBits(ref error) => fmt::Display::fmt(error, f),
+            }
+        }
+    }
+
+    impl<T: Bits + fmt::Debug> core::error::Error for BytesError<T> {}
 }
 
-#[cfg(not(feature = "panic"))]
-mod error {
-    #[derive(Debug)]
-    pub struct Error<T> {
-        pub expected: T,
-        pub provided: T,
+#[doc(hidden)]
+pub mod bytes;
+mod diagram;
+
+#[doc(hidden)]
+pub use diagram::{fmt_bits, fmt_pattern_bits, DiagramCell};
+pub use diagram::{Diagram, InvalidRowBits, RowBits};
+pub use error::{BytesError, Error};
+
+#[doc(hidden)]
+pub use bitrange_plugin::bitrange as __bitrange;
+#[doc(hidden)]
+pub use bitrange_plugin::bitrange_diagram as __bitrange_diagram;
+pub use bitrange_plugin::BitrangeEnum;
+
+/// The bits of a field do not match any of the variants of the enum that the field is mapped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidValue {
+    pub value: u128,
+}
+
+impl InvalidValue {
+    pub const fn new(value: u128) -> InvalidValue {
+        InvalidValue { value }
+    }
+}
+
+/// The value given to a setter does not fit in the bits of the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldOverflow {
+    /// The name of the field
+    pub field: &'static str,
+    /// The amount of bits in the field
+    pub bits: usize,
+}
+
+impl FieldOverflow {
+    pub const fn new(field: &'static str, bits: usize) -> FieldOverflow {
+        FieldOverflow { field, bits }
     }
+}
+
+/// The numbering of the bits of a bitrange struct, given with `#[bitrange(bit_numbering = "...")]`.
+/// This is used for the indices in `Error`, the diagram and the `FIRST_BIT` and `LAST_BIT` constants of the fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BitNumbering {
+    /// Bit 0 is the least significant bit, like the bits of an integer
+    #[default]
+    Lsb0,
+    /// Bit 0 is the most significant bit, like the first bit of the pattern in RFC 791
+    Msb0,
+}
 
-    impl<T> Error<T> {
-        pub fn invalid_bits(expected: T, provided: T) -> Error<T> {
-            Error { expected, provided }
+impl BitNumbering {
+    /// Returns the index of a bit in this numbering, where `bit` is counted from the least significant bit
+    /// and `width` is the amount of bits of the pattern.
+    /// Returns `None` for bits above `width` in `Msb0`, which have no index
+    pub const fn index(self, bit: usize, width: usize) -> Option<usize> {
+        match self {
+            BitNumbering::Lsb0 => Some(bit),
+            BitNumbering::Msb0 if bit < width => Some(width - 1 - bit),
+            BitNumbering::Msb0 => None,
         }
     }
 }
 
-pub use error::Error;
+/// The internal integer of a bitrange struct: `u8` to `u128`, or a `[u8; N]` where the first byte holds the most significant bits.
+/// A width like `u24` is stored in the smallest of these integers that can hold it
+pub trait Bits: Copy {
+    /// The amount of bits
+    const BITS: usize;
+
+    /// Returns true if the bit at `index` is set, where bit 0 is the least significant bit
+    fn bit(&self, index: usize) -> bool;
+}
+
+macro_rules! impl_bits {
+    ($($ty:ty),*) => {
+        $(
+            impl Bits for $ty {
+                const BITS: usize = <$ty>::BITS as usize;
+
+                fn bit(&self, index: usize) -> bool {
+                    self >> index & 1 == 1
+                }
+            }
+        )*
+    };
+}
+
+impl_bits!(u8, u16, u32, u64, u128);
+
+impl<const N: usize> Bits for [u8; N] {
+    const BITS: usize = N * 8;
+
+    fn bit(&self, index: usize) -> bool {
+        self[N - 1 - index / 8] >> (index % 8) & 1 == 1
+    }
+}
+
+/// A field of a `strict_builder()` that has been given a value
+pub struct Set;
+
+/// A field of a `strict_builder()` that has not been given a value yet
+pub struct Unset;
+
+/// A type that can be stored in a field of `BITS` bits, e.g. `b: protocol as Protocol`.
+///
+/// This is implemented by `#[derive(BitrangeEnum)]` for every width, where `FITS` rejects the fields that can not
+/// hold all the variants, and by every bitrange struct of at most 128 bits for a field with exactly as many bits,
+/// e.g. `f: flags as Ipv4Flags`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` does not fit in a field of {BITS} bits",
+    label = "an enum needs room for its largest variant, a bitrange struct needs exactly {BITS} bits"
+)]
+pub trait Field<const BITS: usize>: Sized {
+    /// The type that is returned by the getter of the field.
+    /// This is `Self` if every value of `BITS` bits is a variant, and `Result<Self, InvalidValue>` otherwise
+    type Output;
+
+    /// Fails to compile if the type does not fit in a field of `BITS` bits.
+    /// Every bitrange struct evaluates this for the type of each of its fields
+    const FITS: () = ();
+
+    /// The bits of the field that are always 0 or 1, like the `0` and `1` in the pattern of a bitrange struct.
+    /// These are checked by the struct that has the field, just like its own `0` and `1`
+    const DEFAULT_MASK: u128 = 0;
+    /// The value of the bits in `DEFAULT_MASK`
+    const DEFAULT_VALUE: u128 = 0;
+
+    fn from_bits(bits: u128) -> Self::Output;
+    fn into_bits(self) -> u128;
+
+    /// Format the bits of a field of this type, for the `Debug` implementation of a bitrange struct
+    fn fmt_bits(bits: u128, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&bits, f)
+    }
+
+    /// Format the bits of a field of this type in a cell of `to_diagram`, which is `fmt_bits` by default
+    fn fmt_cell(bits: u128, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        Self::fmt_bits(bits, f)
+    }
+}
+
+/// Formats the bits of a field with `Field::fmt_bits`, so the type of the field does not have to implement `Debug`
+#[doc(hidden)]
+pub struct DebugField<T, const BITS: usize> {
+    bits: u128,
+    field: core::marker::PhantomData<T>,
+}
+
+impl<T, const BITS: usize> DebugField<T, BITS> {
+    pub fn new(bits: u128) -> DebugField<T, BITS> {
+        DebugField {
+            bits,
+            field: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Field<BITS>, const BITS: usize> core::fmt::Debug for DebugField<T, BITS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        T::fmt_bits(self.bits, f)
+    }
+}
+
+/// Formats the bits of a field with `Field::fmt_cell`, for the cells of `to_diagram`
+#[doc(hidden)]
+pub struct DiagramField<T, const BITS: usize>(DebugField<T, BITS>);
+
+impl<T, const BITS: usize> DiagramField<T, BITS> {
+    pub fn new(bits: u128) -> DiagramField<T, BITS> {
+        DiagramField(DebugField::new(bits))
+    }
+}
+
+impl<T: Field<BITS>, const BITS: usize> core::fmt::Debug for DiagramField<T, BITS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        T::fmt_cell(self.0.bits, f)
+    }
+}
 
 /// Create a bitrange struct.
 ///
@@ -53,121 +323,767 @@ pub use error::Error;
 /// #![deny(warnings)]
 /// #[macro_use]
 /// extern crate bitrange;
-/// #[macro_use]
-/// extern crate bitrange_plugin;
 /// # fn main() {
 /// bitrange! {
-///     Test: u8, "u8",         // the name of the struct and the size of the internal integer
-///     "aaa1_0bbb",            // the format of the bits in the internal integer
+///     Test: u8,               // the name of the struct and the size of the internal integer
+///     aaa1_0bbb,              // the format of the bits in the internal integer
 ///     a: first,               // map the bits that are marked as `a` to field `first`
 ///     b: second set_second    // map the bits that are marked as `b` to field `second`
 ///                             // and create a setter `set_second` that sets a given value to `b`
 /// }
 /// # }
 /// ```
+///
+/// The older form, where the size is repeated as a string and the format is quoted, still works:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8, "u8",
+///     "aaa1_0bbb",
+///     a: first,
+///     b: second set_second
+/// }
+/// # }
+/// ```
+///
+/// Mistakes in the pattern or in the field mapping are reported at compile time:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0b-bb, // error: Test: invalid character '-' in pattern "aaa1_0b-bb"
+///     a: first
+/// }
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second,
+///     c: third // error: Test: field 'c' is not found in pattern "aaa1_0bbb"
+/// }
+/// # }
+/// ```
+///
+/// Getters return the smallest integer type that fits the field, and setters take that same type.
+/// A different type can be given with `as`:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u32,
+///     aaaa_bbbb_cccccccc_dddddddddddddddd,
+///     a: first,                 // fn first(&self) -> u8
+///     b: second as u32,         // fn second(&self) -> u32
+///     c: third,                 // fn third(&self) -> u8
+///     d: fourth set_fourth      // fn fourth(&self) -> u16 and fn set_fourth(&mut self, value: u16)
+/// }
+/// # }
+/// ```
+///
+/// The type has to be an integer type, a width like `u4` can only be the size of a struct:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaaa_bbbb,
+///     a: first as u4, // error: Test: invalid type u4 for field `first`, expected an integer type like u8 or i8
+///     b: second
+/// }
+/// # }
+/// ```
+///
+/// Fields with a single bit are a `bool`. If they have a setter, `toggle_x`, `set_x_on` and `clear_x` are generated as well.
+/// Use `as u8` to keep the numeric form:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaaa_aabc,
+///     a: first,
+///     b: flag set_flag,         // fn flag(&self) -> bool, set_flag, toggle_flag, set_flag_on and clear_flag
+///     c: bit as u8              // fn bit(&self) -> u8
+/// }
+/// let mut test = Test::default();
+/// test.toggle_flag();
+/// assert!(test.flag());
+/// # }
+/// ```
+///
+/// Fields marked as `signed`, or with a signed type like `as i32`, are sign-extended:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaaa_bbbb,
+///     a: offset set_offset signed,  // fn offset(&self) -> i8
+///     b: reading as i32             // fn reading(&self) -> i32
+/// }
+/// let mut test = Test::default();
+/// test.set_offset(-3);
+/// assert_eq!(test.offset(), -3);
+/// # }
+/// ```
+///
+/// The bits of a field that is spread over the pattern are packed in the order they appear in the pattern.
+/// A different order is given after the getter, with the index of every bit in the value from the first to the last
+/// bit in the pattern, like the immediate of a RISC-V branch. Bits below the lowest index are always 0:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     BType: u32,
+///     iiiiiii_bbbbb_aaaaa_fff_iiiii_ooooooo,
+///     i: imm[12|10:5|4:1|11] set_imm signed, // fn imm(&self) -> i16
+///     b: rs2,
+///     a: rs1,
+///     f: funct3,
+///     o: opcode
+/// }
+/// let mut branch = BType::try_from(0xfe20_8ce3).unwrap(); // beq x1, x2, -8
+/// assert_eq!(branch.imm(), -8);
+/// assert!(branch.try_set_imm(3).is_err());
+/// # }
+/// ```
+///
+/// Numeric fields with a setter `set_x` also get `try_set_x`, which returns a `FieldOverflow` error if the value
+/// does not fit in the field, `set_x_wrapping`, which truncates the value, and `set_x_saturating`, which clamps it.
+/// `set_x` itself checks that the value fits with a `debug_assert!`, so like the arithmetic of integers,
+/// it panics in debug builds and truncates the value in release builds:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaaa_bbbb,
+///     a: first set_first,
+///     b: offset set_offset signed
+/// }
+/// let mut test = Test::default();
+/// assert!(test.try_set_first(16).is_err());
+/// test.set_first_wrapping(0x12).set_offset_saturating(-100);
+/// assert_eq!((test.first(), test.offset()), (0x2, -8));
+/// # }
+/// ```
+///
+/// The internal integer is converted with `TryFrom` and `From`, which check the bits that are always 0 or 1.
+/// `bits()` returns the internal integer, and `from_bits_unchecked` skips the check:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// let test = Test::try_from(0b1011_0011).unwrap();
+/// assert_eq!(test.first(), 0b101);
+/// assert_eq!(test.bits(), 0b1011_0011);
+/// assert_eq!(u8::from(test), 0b1011_0011);
+///
+/// const RAW: Test = Test::from_bits_unchecked(0b1010_0011);
+/// assert_eq!(RAW.second(), 0b011);
+/// # }
+/// ```
+///
+/// The `const fn` constructors `try_from_bits`, `from_bits_or_panic` and `from_bits_masked` return an error,
+/// panic or set the bits that are always 0 or 1 when they don't match:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// const TEST: Test = Test::from_bits_or_panic(0b1011_0011);
+/// assert!(Test::try_from_bits(0b1010_0011).is_err());
+/// assert_eq!(Test::from_bits_masked(0b1010_0011), TEST);
+/// # }
+/// ```
+///
+/// `from_be_bytes`, `from_le_bytes` and `from_ne_bytes` create a value from the bytes of the internal integer,
+/// and `to_be_bytes`, `to_le_bytes` and `to_ne_bytes` return them. `TryFrom<&[u8]>` and `From<Struct> for [u8; N]`
+/// are big-endian, unless a different byte order is given with `#[bitrange(byte_order = "...")]`:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     IpHeader: u32,
+///     aaaa_bbbb_cccccccc_dddddddddddddddd,
+///     a: version,
+///     b: ihl,
+///     c: type_of_service,
+///     d: total_length
+/// }
+/// let header = IpHeader::try_from(&[0x45, 0x00, 0x05, 0xdc][..]).unwrap();
+/// assert_eq!(header.total_length(), 1500);
+/// assert_eq!(header.to_le_bytes(), [0xdc, 0x05, 0x00, 0x45]);
+///
+/// bitrange! {
+///     #[bitrange(byte_order = "little")]
+///     Register: u16,
+///     aaaa_aaaa_bbbb_bbbb,
+///     a: high,
+///     b: low
+/// }
+/// let register = Register::try_from(&[0x34, 0x12][..]).unwrap();
+/// assert_eq!(register.high(), 0x12);
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     #[bitrange(byte_order = "middle")] // error: Test: invalid byte order "middle", expected "big", "little" or "native"
+///     Test: u8,
+///     aaaa_bbbb,
+///     a: first,
+///     b: second
+/// }
+/// # }
+/// ```
+///
+/// The internal integer is one of `u8`, `u16`, `u32`, `u64` and `u128`, or a width in between like `u24` or `u48`.
+/// A width is stored in the smallest integer that can hold it, and the bits above it are always 0.
+/// Its byte conversions use as many bytes as the width needs:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Register: u24,
+///     r_aaaaaaa_dddddddddddddddd,
+///     r: read,
+///     a: address,
+///     d: data
+/// }
+/// let register = Register::from_be_bytes([0x85, 0x12, 0x34]).unwrap();
+/// assert_eq!((register.read(), register.address(), register.data()), (true, 5, 0x1234));
+///
+/// let bits: u32 = register.bits();
+/// assert_eq!(bits, 0x0085_1234);
+/// assert_eq!(register.to_le_bytes(), [0x34, 0x12, 0x85]);
+/// assert_eq!(
+///     Register::try_from(0x0100_0000).unwrap_err().to_string(),
+///     "Register: bit 24 must be 0"
+/// );
+/// # }
+/// ```
+///
+/// Patterns that are wider than a `u128` can be stored in a `[u8; N]`, where the first byte holds the first bits of the pattern.
+/// Fields can cross the boundaries of the bytes, and can have up to 128 bits. Bit 0 is the least significant bit of the last byte:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Header: [u8; 3],
+///     aaaa_bbbbbbbbbbbb_0ccccccc,
+///     a: version,
+///     b: length set_length,
+///     c: checksum
+/// }
+/// let mut header = Header::try_from(&[0x40, 0x05, 0x7f][..]).unwrap();
+/// assert_eq!((header.version(), header.length(), header.checksum()), (4, 5, 0x7f));
+///
+/// header.set_length(0xabc);
+/// assert_eq!(header.bits(), [0x4a, 0xbc, 0x7f]);
+/// assert_eq!(format!("{:x}", header), "4abc7f");
+///
+/// let error = Header::try_from([0x40, 0x05, 0xff]).unwrap_err();
+/// assert_eq!(error.to_string(), "Header: bit 7 must be 0");
+/// # }
+/// ```
+///
+/// Values can be created with a positional `const fn new`, which takes every field in the order they are mapped,
+/// or with a builder that starts from the default value. Fields that are not given to `builder()` keep their default value:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// const TEST: Test = Test::new(0b101, 0b011);
+/// assert_eq!(TEST.first(), 0b101);
+///
+/// let test = Test::builder().second(0b011).build();
+/// assert_eq!(test.second(), 0b011);
+///
+/// let test = Test::strict_builder().first(0b101).second(0b011).build();
+/// assert_eq!(test.first(), 0b101);
+/// # }
+/// ```
+///
+/// `strict_builder()` can only be built once every field has been given a value:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// let test = Test::strict_builder().first(0b101).build(); // error: no method named `build` found for struct `TestStrictBuilder<Set, Unset>`
+/// # }
+/// ```
+///
+/// Every field has associated constants with its mask, the index of its lowest bit and its width, named after the
+/// character in uppercase. Getters, setters, `from`, `new` and the builders are `const fn`, except for fields that are
+/// mapped to an enum, so values can be created at compile time. `new` and the builders check every value with an `assert!`,
+/// also in release builds, so a value that does not fit in its field is a compile error in a const.
+/// `set_x` only checks the value in debug builds:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second set_second
+/// }
+/// assert_eq!((Test::A_MASK, Test::A_OFFSET, Test::A_WIDTH), (0b1110_0000, 5, 3));
+///
+/// const TEST: Test = Test::builder().first(0b101).build();
+/// const FIRST: u8 = TEST.first();
+/// assert_eq!(FIRST, 0b101);
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// const TEST: Test = Test::new(0b1000, 0); // error: Test: value does not fit in the 3 bits of field `first`
+/// # }
+/// ```
+///
+/// A field can be mapped to an enum with `#[derive(BitrangeEnum)]`. The getter returns `Result<Protocol, InvalidValue>`,
+/// or just the enum if every value of the field is a variant:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// #[derive(BitrangeEnum, Debug, PartialEq)]
+/// enum Protocol {
+///     Icmp = 1,
+///     Tcp = 6,
+///     Udp = 17,
+/// }
+///
+/// #[derive(BitrangeEnum, Debug, PartialEq)]
+/// enum Precedence {
+///     Routine,
+///     Priority,
+///     Immediate,
+///     Flash,
+/// }
+///
+/// bitrange! {
+///     Test: u16,
+///     aabb_bbbb_cccc_cccc,
+///     a: precedence as Precedence,                // fn precedence(&self) -> Precedence
+///     b: _,
+///     c: protocol set_protocol as Protocol        // fn protocol(&self) -> Result<Protocol, InvalidValue>
+/// }
+/// let mut test = Test::default();
+/// test.set_protocol(Protocol::Udp);
+/// assert_eq!(test.protocol(), Ok(Protocol::Udp));
+/// assert_eq!(test.precedence(), Precedence::Routine);
+/// # }
+/// ```
+///
+/// Another bitrange struct can be the type of a field with exactly as many bits. The getter returns the struct,
+/// and the `0` and `1` in its pattern are checked by `try_from` of the struct that has the field:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Flags: u3,
+///     0dm,
+///     d: dont_fragment,
+///     m: more_fragments
+/// }
+///
+/// bitrange! {
+///     Fragment: u16,
+///     fff_ooooooooooooo,
+///     f: flags set_flags as Flags,
+///     o: offset
+/// }
+/// let fragment = Fragment::try_from(0x40b9).unwrap();
+/// assert!(fragment.flags().dont_fragment());
+/// assert_eq!(
+///     Fragment::try_from(0x80b9).unwrap_err().to_string(),
+///     "Fragment: bit 15 must be 0"
+/// );
+/// # }
+/// ```
+///
+/// Every variant has to fit in the field:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// #[derive(BitrangeEnum)]
+/// enum Protocol {
+///     Icmp = 1,
+///     Tcp = 6,
+///     Udp = 17,
+/// }
+///
+/// bitrange! {
+///     Test: u8,
+///     aaaa_bbbb,
+///     a: version,
+///     b: protocol as Protocol // error: `Protocol` needs a field of at least 5 bits
+/// }
+/// # }
+/// ```
+///
+/// The struct implements `Clone`, `Copy`, `PartialEq`, `Eq`, `Hash`, `Debug`, which prints every named field,
+/// `Display`, which draws the diagram of `to_diagram(RowBits::B32)`, and `Binary`, `LowerHex`, `UpperHex` and `Octal`,
+/// which format the internal integer.
+/// Use `#[bitrange(derive(...))]` to only implement some of these, e.g. to implement `Debug` by hand:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// assert_eq!(format!("{:?}", Test::new(5, 3)), "Test { first: 5, second: 3 }");
+///
+/// bitrange! {
+///     #[bitrange(derive(Clone, Copy))]
+///     Raw: u8,
+///     aaaa_aaaa,
+///     a: value
+/// }
+///
+/// impl std::fmt::Debug for Raw {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "Raw({})", self.value())
+///     }
+/// }
+/// # }
+/// ```
+///
+/// `{:#b}` groups the bits like the pattern, and marks which field every bit belongs to:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// assert_eq!(
+///     format!("{:#b}", Test::new(5, 3)),
+///     "0b1011_0011\n  aaa1_0bbb  a: first, b: second"
+/// );
+/// # }
+/// ```
+///
+/// `to_diagram(row_bits)` draws the fields in an RFC style diagram, with the name of every field above its value.
+/// A row has 8, 16 or 32 bits, given with `RowBits::B8`, `RowBits::B16` or `RowBits::B32`,
+/// and `RowBits::try_from(bits)` returns an `InvalidRowBits` for any other amount of bits.
+/// Fields that do not fit in the rest of a row continue on the next one. The header has the index of every bit in the first row:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u16,
+///     aaaa_aaaa_bbbb_bbbc,
+///     a: first,
+///     b: second,
+///     c: flag
+/// }
+/// let test = Test::new(1, 2, true);
+/// assert_eq!(
+///     test.to_diagram(bitrange::RowBits::B8).to_string(),
+///     [
+///         "           1",
+///         " 5 4 3 2 1 0 9 8",
+///         "+-+-+-+-+-+-+-+-+",
+///         "|     first     |",
+///         "|       1       |",
+///         "+-+-+-+-+-+-+-+-+",
+///         "|   second    |f|",
+///         "|      2      |1|",
+///         "+-+-+-+-+-+-+-+-+",
+///     ]
+///     .join("\n")
+/// );
+/// # }
+/// ```
+///
+/// Bit 0 is the least significant bit, unless the struct has `#[bitrange(bit_numbering = "msb0")]`.
+/// Then bit 0 is the first bit of the pattern, like in RFC 791, and the diagram, the error messages,
+/// `Error::positions()` and the `FIRST_BIT` and `LAST_BIT` constants of every field use that numbering.
+/// `OFFSET` is always counted from the least significant bit, because it is the amount of bits that a field is shifted:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     #[bitrange(bit_numbering = "msb0")]
+///     Fragment: u16,
+///     0dm_ooooooooooooo,
+///     d: dont_fragment,
+///     m: more_fragments,
+///     o: offset
+/// }
+/// assert_eq!((Fragment::O_FIRST_BIT, Fragment::O_LAST_BIT), (3, 15));
+/// assert_eq!(Fragment::O_OFFSET, 0);
+/// assert_eq!(
+///     Fragment::try_from(0x8000).unwrap_err().to_string(),
+///     "Fragment: bit 0 must be 0"
+/// );
+/// assert!(Fragment::default().to_string().starts_with(" 0                   1\n 0 1 2 3"));
+/// # }
+/// ```
+///
+/// A run of the same character can be shortened to the character and the amount of bits, like `a{4}`.
+/// Instead of a pattern, the fields can also be declared by their amount of bits, from the most significant bit.
+/// `_: 2` are 2 reserved bits, `_: 0b10` are bits that are always `10`, and the associated constants are named after the fields:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Repeated: u16,
+///     a{4}_1{2}b{10},
+///     a: first,
+///     b: second
+/// }
+/// assert_eq!(Repeated::default().bits(), 0b0000_1100_0000_0000);
+///
+/// bitrange! {
+///     Test: u16,
+///     first: 4,
+///     _: 0b11,
+///     second: 10 set_second
+/// }
+/// assert_eq!(Test::new(0, 0).bits(), 0b0000_1100_0000_0000);
+/// assert_eq!((Test::SECOND_OFFSET, Test::SECOND_WIDTH), (0, 10));
+/// # }
+/// ```
+///
+/// Every character in the pattern needs to be mapped to a field. Characters that are not used can be marked as reserved:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb, // error: Test: character 'b' of pattern "aaa1_0bbb" is not mapped
+///     a: first
+/// }
+/// # }
+/// ```
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u8,
+///     aaa1_0bbb,
+///     a: first,
+///     b: _ // `b` is reserved
+/// }
+/// # }
+/// ```
+///
+/// The pattern should have exactly as many bits as the internal integer:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     Test: u16,
+///     aaa1_0bbb, // error: Test: pattern "aaa1_0bbb" has 8 bits, but u16 has 16 bits
+///     a: first
+/// }
+/// # }
+/// ```
+///
+/// Unless `#[bitrange(short)]` is given, in which case the pattern is mapped to the least significant bits:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange! {
+///     #[bitrange(short)]
+///     Test: u16,
+///     aaa1_0bbb,
+///     a: first,
+///     b: second
+/// }
+/// # }
+/// ```
 #[macro_export]
 macro_rules! bitrange {
-    (
-        $struct_name:ident:
-        $struct_size:ty,
-        $struct_size_string:tt,
-        $format:tt,
-        $(
-            $field_format:ident:
-            $(
-                $field_name:ident
-            )+
-        ),+
-    ) => {
-        #[derive(Bitrange)]
-        #[BitrangeMask = $format]
-        #[BitrangeSize = $struct_size_string]
-        pub struct $struct_name {
-            #[allow(dead_code)]
-            bits: $struct_size
-        }
-        impl Default for $struct_name {
-            #[allow(dead_code)]
-            fn default() -> $struct_name {
-                #[allow(dead_code, non_snake_case)]
-                let DEFAULT_VALUE: $struct_size = $struct_name::__bitrange_get_default_value();
-                $struct_name {
-                    bits: DEFAULT_VALUE
-                }
-            }
-        }
-        impl $struct_name {
-            #[allow(dead_code)]
-            pub fn from(bits: $struct_size) -> Result<$struct_name, ::bitrange::Error<$struct_size>> {
-                #[allow(dead_code, non_snake_case)]
-                let DEFAULT_VALUE: $struct_size = $struct_name::__bitrange_get_default_value();
-                #[allow(dead_code, non_snake_case)]
-                let DEFAULT_MASK: $struct_size = $struct_name::__bitrange_get_default_mask();
-
-                if bits & DEFAULT_MASK == DEFAULT_VALUE {
-                    Ok($struct_name {
-                        bits
-                    })
-                } else {
-                    Err(::bitrange::Error::invalid_bits(DEFAULT_VALUE, bits & DEFAULT_MASK))
-                }
-            }
-        }
-        $(
-            bitrange_impl_field!(
-                $struct_name,
-                $struct_size,
-                $format,
-                $field_format,
-                $($field_name),+
-            );
-        )+
-    }
+    ($($tokens:tt)*) => {
+        $crate::__bitrange! { $($tokens)* }
+    };
 }
 
-#[doc(hidden)]
+/// Create a bitrange struct from an RFC style diagram, like the ones in RFC 791.
+///
+/// The diagram is given as a string, because the `|` and `+-+` of the diagram are not valid tokens.
+/// Every bit is two characters wide, the lines with the indices of the bits are skipped.
+/// The label of every cell is the name of the getter in snake case, e.g. `type_of_service` for `Type of Service`,
+/// and every field gets a setter with `set_` in front of that name.
+/// Cells that are empty or labeled `Reserved` or `Unused` are reserved, and cells with only `0` or `1` are fixed bits.
+/// Cells with the same label are a single field, and a cell without any `|` between lines that start with `+`
+/// spans multiple rows, like the addresses in RFC 8200.
+///
+/// The struct is the same as the one `bitrange!` creates, except that the bits are numbered from the first bit of the diagram,
+/// unless the struct has `#[bitrange(bit_numbering = "lsb0")]`:
+///
+/// ```rust
+/// #![deny(warnings)]
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange_diagram! {
+///     Ipv4Flags: u16,
+///     r"
+///      0                   1
+///      0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+///     +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///     |0|D|M|     Fragment Offset     |
+///     | |F|F|                         |
+///     +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///     "
+/// }
+/// let mut flags = Ipv4Flags::default();
+/// flags.set_df(true).set_fragment_offset(185);
+/// assert_eq!(flags.bits(), 0x40b9);
+/// assert!(!flags.mf());
+/// assert_eq!(
+///     Ipv4Flags::try_from(0x8000).unwrap_err().to_string(),
+///     "Ipv4Flags: bit 0 must be 0"
+/// );
+/// # }
+/// ```
+///
+/// Every `|` has to be between two bits, so every cell is a whole amount of bits:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate bitrange;
+/// # fn main() {
+/// bitrange_diagram! {
+///     Test: u8,
+///     r"
+///     +-+-+-+-+-+-+-+-+
+///     |Firs|t|Second  |
+///     +-+-+-+-+-+-+-+-+
+///     " // error: Test: the | in line 3 of the diagram should be between two bits
+/// }
+/// # }
+/// ```
 #[macro_export]
-macro_rules! bitrange_impl_field {
-    ($struct_name:ident, $struct_size:tt, $format:tt, $field_format:ident, $field_get:ident) => {
-        impl $struct_name {
-            pub fn $field_get(&self) -> $struct_size {
-                #[allow(dead_code, non_snake_case)]
-                let MASK: $struct_size =
-                    $struct_name::__bitrange_get_mask(stringify!($field_format));
-                #[allow(dead_code, non_snake_case)]
-                let OFFSET: usize = $struct_name::__bitrange_get_offset(stringify!($field_format));
-
-                (self.bits & MASK) >> OFFSET
-            }
-        }
-    };
-    (
-        $struct_name:ident,
-        $struct_size:tt,
-        $format:tt,
-        $field_format:ident,
-        $field_get:ident,
-        $field_set:ident
-    ) => {
-        bitrange_impl_field!(
-            $struct_name,
-            $struct_size,
-            $format,
-            $field_format,
-            $field_get
-        );
-
-        impl $struct_name {
-            pub fn $field_set(&mut self, value: $struct_size) -> &mut Self {
-                #[allow(dead_code, non_snake_case)]
-                let MASK: $struct_size =
-                    $struct_name::__bitrange_get_mask(stringify!($field_format));
-                #[allow(dead_code, non_snake_case)]
-                let OFFSET: usize = $struct_name::__bitrange_get_offset(stringify!($field_format));
-                self.bits &= !MASK;
-                self.bits |= (value << OFFSET) & MASK;
-                self
-            }
-        }
+macro_rules! bitrange_diagram {
+    ($($tokens:tt)*) => {
+        $crate::__bitrange_diagram! { $($tokens)* }
     };
 }